@@ -1,28 +1,11 @@
-use std::fmt;
-use std::fmt::Formatter;
+use ggez::graphics::Rect;
 
-use ggez::graphics::{Image, Rect};
-
-use crate::constants::{GROUND_TILE_HEIGHT, GROUND_TILE_WIDTH};
-use crate::game::SpriteGame;
-use crate::level_handler::TileType;
+use crate::constants::{
+    GROUND_TILE_HEIGHT, GROUND_TILE_WIDTH, PLAYER_BBOX_HEIGHT, PLAYER_BBOX_WIDTH,
+    PLAYER_TILE_HEIGHT, PLAYER_TILE_WIDTH,
+};
 use crate::primitives::{Dimensions, Direction, Point2};
 
-#[derive(Debug)]
-pub enum ActorType {
-    Player,
-    GroundBlock { x: usize, y: usize },
-}
-
-impl fmt::Display for ActorType {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match *self {
-            ActorType::Player => write!(f, "Player"),
-            ActorType::GroundBlock { x, y } => write!(f, "Ground({},{})", x, y),
-        }
-    }
-}
-
 /// Regarding game space:
 /// Game space is pixels. Y coordinate axis direction is inverted from the draw direction
 /// to make game physics more intuitive (-y is down).
@@ -38,7 +21,6 @@ impl fmt::Display for ActorType {
 
 #[derive(Debug)]
 pub struct Actor {
-    pub tag: ActorType,
     /// Current position of the actor's bounding box's bottom left corner in game space.q
     /// Note: If bbox_offset is not {0,0}, the sprite will be drawn to a different location.
     pub pos: Point2,
@@ -54,13 +36,10 @@ pub struct Actor {
     pub bbox: Rect,
 }
 impl Actor {
-    pub fn create_ground(tile: &TileType, x: usize, y: usize) -> Actor {
+    /// Creates a ground block that fills the given level tile.
+    pub fn create_ground(x: usize, y: usize) -> Actor {
         let pos = Point2::new(x as f32 * GROUND_TILE_WIDTH, y as f32 * GROUND_TILE_HEIGHT);
         Actor {
-            tag: ActorType::GroundBlock {
-                x: tile.x,
-                y: tile.y,
-            },
             pos,
             facing: Direction::Right,
             sprite_size: Dimensions::new(GROUND_TILE_WIDTH, GROUND_TILE_HEIGHT),
@@ -74,6 +53,23 @@ impl Actor {
         }
     }
 
+    /// Creates a knight-sized character standing at the given position.
+    pub fn create_knight(pos: Point2, facing: Direction) -> Actor {
+        let bbox = Dimensions::new(PLAYER_BBOX_WIDTH, PLAYER_BBOX_HEIGHT);
+        Actor {
+            pos,
+            facing,
+            sprite_size: Dimensions::new(PLAYER_TILE_WIDTH, PLAYER_TILE_HEIGHT),
+            draw_offset: Point2::new((bbox.x - PLAYER_TILE_WIDTH) / 2.0, 0.0),
+            bbox: Rect {
+                x: pos.x,
+                y: pos.y,
+                w: bbox.x,
+                h: bbox.y,
+            },
+        }
+    }
+
     pub fn update_bbox(&mut self) {
         self.bbox.x = self.pos.x;
         self.bbox.y = self.pos.y;
@@ -88,24 +84,4 @@ impl Actor {
             h: self.sprite_size.y,
         }
     }
-
-    /// Returns the offset of this tile to draw this actor from its tile image
-    pub fn tile_offset(&self, image: &Image, game: &SpriteGame) -> Rect {
-        let x_size = self.sprite_size.x / image.width() as f32;
-        let y_size = self.sprite_size.y / image.height() as f32;
-        match self.tag {
-            ActorType::Player => Rect {
-                x: game.player.animation.get_current_frame() as f32 * x_size,
-                y: 0.0,
-                w: x_size,
-                h: y_size,
-            },
-            ActorType::GroundBlock { x, y } => Rect {
-                x: x as f32 * x_size,
-                y: y as f32 * y_size,
-                w: x_size,
-                h: y_size,
-            },
-        }
-    }
 }
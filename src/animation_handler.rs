@@ -1,79 +1,456 @@
 use std::cmp::min;
+use std::collections::HashMap;
+use std::io;
+use std::io::BufRead;
+use std::rc::Rc;
 
-use ggez::graphics::Image;
+use ggez::{Context, GameError, GameResult};
 
-use crate::game_assets::GameAssets;
+use crate::animator::{Animator, Transition, Transitions};
+use crate::primitives::Dimensions;
+
+/// Animation definition, loaded from the animation data file.
+#[derive(Debug)]
+pub struct AnimationDef {
+    pub name: String,
+    /// Path to the sprite sheet image, relative to the resources directory
+    pub sheet: String,
+    /// Size of a single frame in the sprite sheet, in pixels
+    pub frame_size: Dimensions,
+    /// Frame indices in the sprite sheet, numbered row by row from the upper left corner
+    pub frames: Vec<usize>,
+    /// Duration of each frame, in seconds
+    pub durations: Vec<f32>,
+    pub mode: PlaybackMode,
+    /// Events triggered when a frame is entered, as (frame position, event name) pairs
+    pub events: Vec<(usize, Rc<str>)>,
+}
+
+/// Named animation definitions, loaded from a data file.
+#[derive(Debug, Default)]
+pub struct AnimationLibrary {
+    animations: HashMap<String, Rc<AnimationDef>>,
+    transitions: Rc<Transitions>,
+}
+
+/// Transition rule as read from the data file, before animations are resolved.
+enum TransitionLine {
+    KeepPhase(String, String),
+    Clip(String, String, String),
+}
+
+impl AnimationLibrary {
+    pub fn load(ctx: &Context, file: &str) -> GameResult<AnimationLibrary> {
+        let reader = io::BufReader::new(ctx.fs.open(file)?);
+        let mut defs: Vec<AnimationDef> = Vec::new();
+        let mut transition_lines = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let result = if let Some(event) = line.strip_prefix("event ") {
+                Self::parse_event(event, &mut defs)
+            } else if let Some(transition) = line.strip_prefix("transition ") {
+                Self::parse_transition(transition, &defs).map(|t| transition_lines.push(t))
+            } else {
+                Self::parse_def(line).map(|def| defs.push(def))
+            };
+            result.map_err(|e| {
+                GameError::ResourceLoadError(format!("{}:{}: {}", file, index + 1, e))
+            })?;
+        }
+        let animations: HashMap<String, Rc<AnimationDef>> = defs
+            .into_iter()
+            .map(|def| (def.name.clone(), Rc::new(def)))
+            .collect();
+        let transitions = transition_lines
+            .into_iter()
+            .map(|t| match t {
+                TransitionLine::KeepPhase(from, to) => ((from, to), Transition::KeepPhase),
+                TransitionLine::Clip(from, to, clip) => {
+                    ((from, to), Transition::Clip(animations[&clip].clone()))
+                }
+            })
+            .collect();
+        Ok(AnimationLibrary {
+            animations,
+            transitions: Rc::new(transitions),
+        })
+    }
+
+    /// Parses a transition line (`from to keep_phase` or `from to clip clip_name`).
+    /// All referenced animations must already be defined.
+    fn parse_transition(line: &str, defs: &[AnimationDef]) -> Result<TransitionLine, String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let find = |name: &str| {
+            defs.iter()
+                .find(|d| d.name == name)
+                .ok_or_else(|| format!("transition uses undefined animation {}", name))
+        };
+        match fields[..] {
+            [from, to, "keep_phase"] => {
+                find(from)?;
+                find(to)?;
+                Ok(TransitionLine::KeepPhase(from.to_string(), to.to_string()))
+            }
+            [from, to, "clip", clip] => {
+                find(from)?;
+                find(to)?;
+                if !find(clip)?.mode.completes() {
+                    return Err(format!("transition clip {} must be once or hold", clip));
+                }
+                Ok(TransitionLine::Clip(
+                    from.to_string(),
+                    to.to_string(),
+                    clip.to_string(),
+                ))
+            }
+            _ => Err(String::from(
+                "expected transition fields: from to keep_phase, or from to clip clip_name",
+            )),
+        }
+    }
+
+    /// Parses an event line (`animation event_name frames`), and adds
+    /// the event to an already defined animation.
+    fn parse_event(line: &str, defs: &mut [AnimationDef]) -> Result<(), String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [animation, name, frames] = fields[..] else {
+            return Err(format!("expected 3 event fields, found {}", fields.len()));
+        };
+        let def = defs
+            .iter_mut()
+            .find(|d| d.name == animation)
+            .ok_or_else(|| format!("event for undefined animation {}", animation))?;
+        let name: Rc<str> = Rc::from(name);
+        for frame in Self::parse_frames(frames)? {
+            if frame >= def.frames.len() {
+                return Err(format!("event frame {} out of range", frame));
+            }
+            def.events.push((frame, name.clone()));
+        }
+        Ok(())
+    }
+
+    fn parse_def(line: &str) -> Result<AnimationDef, String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [name, sheet, width, height, frames, durations, mode] = fields[..] else {
+            return Err(format!("expected 7 fields, found {}", fields.len()));
+        };
+        let frames = Self::parse_frames(frames)?;
+        let durations = Self::parse_list::<f32>(durations)?;
+        let durations = match durations.len() {
+            1 => vec![durations[0]; frames.len()],
+            n if n == frames.len() => durations,
+            n => return Err(format!("{} durations given for {} frames", n, frames.len())),
+        };
+        if durations.iter().any(|d| *d <= 0.0) {
+            return Err(String::from("frame durations must be positive"));
+        }
+        let mode = PlaybackMode::parse(mode)?;
+        Ok(AnimationDef {
+            name: name.to_string(),
+            sheet: sheet.to_string(),
+            frame_size: Dimensions::new(Self::parse(width)?, Self::parse(height)?),
+            frames,
+            durations,
+            mode,
+            events: Vec::new(),
+        })
+    }
+
+    /// Parses a frame range (`0-7`) or a comma-separated frame list (`0,1,2`).
+    fn parse_frames(frames: &str) -> Result<Vec<usize>, String> {
+        let list = match frames.split_once('-') {
+            Some((first, last)) => (Self::parse(first)?..=Self::parse(last)?).collect(),
+            None => Self::parse_list(frames)?,
+        };
+        if list.is_empty() {
+            return Err(format!("no frames in {}", frames));
+        }
+        Ok(list)
+    }
+
+    fn parse_list<T: std::str::FromStr>(list: &str) -> Result<Vec<T>, String> {
+        list.split(',').map(Self::parse).collect()
+    }
+
+    fn parse<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+        value
+            .parse()
+            .map_err(|_| format!("invalid value {}", value))
+    }
+
+    /// Creates an animator that starts playing the named animation, and uses
+    /// the library's transition rules when switching animations.
+    /// Panics if the animation is not defined, as that is an error in the game data.
+    pub fn create(&self, name: &str) -> Animator {
+        Animator::new(self.get(name), self.transitions.clone())
+    }
+
+    /// Returns the named animation, or `None` if it is not defined.
+    pub fn find(&self, name: &str) -> Option<Rc<AnimationDef>> {
+        self.animations.get(name).cloned()
+    }
+
+    pub fn get(&self, name: &str) -> Rc<AnimationDef> {
+        self.animations
+            .get(name)
+            .unwrap_or_else(|| panic!("Animation {} is not defined", name))
+            .clone()
+    }
+
+    /// Returns the sprite sheet paths of all defined animations.
+    pub fn sheets(&self) -> impl Iterator<Item = &str> {
+        self.animations.values().map(|a| a.sheet.as_str())
+    }
+}
+
+/// Event emitted by `Animation::update` when a non-looping animation completes.
+pub const COMPLETE_EVENT: &str = "complete";
+
+/// How an animation advances through its frames.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PlaybackMode {
+    /// Plays from first to last frame, and starts again from the first frame.
+    Loop,
+    /// Plays from first to last frame once, and then returns to the first frame.
+    Once,
+    /// Plays from first to last frame once, and then holds the last frame.
+    OnceHold,
+    /// Plays from first to last frame and back again, repeatedly.
+    PingPong,
+    /// Plays from last to first frame, and starts again from the last frame.
+    Reverse,
+}
+
+impl PlaybackMode {
+    fn parse(mode: &str) -> Result<PlaybackMode, String> {
+        match mode {
+            "loop" => Ok(PlaybackMode::Loop),
+            "once" => Ok(PlaybackMode::Once),
+            "hold" => Ok(PlaybackMode::OnceHold),
+            "pingpong" => Ok(PlaybackMode::PingPong),
+            "reverse" => Ok(PlaybackMode::Reverse),
+            _ => Err(format!("unknown mode {}", mode)),
+        }
+    }
+
+    /// Returns true for modes that stop after playing all frames once.
+    pub fn completes(&self) -> bool {
+        matches!(self, PlaybackMode::Once | PlaybackMode::OnceHold)
+    }
+}
 
 #[derive(Debug)]
 pub struct Animation {
-    pub tileset_image: fn(&GameAssets) -> &Image,
-    frame_count: usize,
-    frame_duration: f32,
+    pub def: Rc<AnimationDef>,
+    /// Number of frames advanced since the animation started
     current_frame: usize,
     elapsed_time: f32,
-    loop_animation: bool,
+    /// Playback speed multiplier; 1.0 plays the animation at its defined frame durations
+    speed: f32,
+    /// Set when the events of the first frame have been triggered
+    started: bool,
 }
 
 impl Animation {
-    pub fn player_idle() -> Animation {
+    pub fn new(def: Rc<AnimationDef>) -> Animation {
         Animation {
-            tileset_image: |a| &a.player_idle_tiles,
-            frame_count: 8,
-            frame_duration: 0.13,
+            def,
             current_frame: 0,
             elapsed_time: 0.0,
-            loop_animation: true,
+            speed: 1.0,
+            started: false,
         }
     }
 
-    pub fn player_walking() -> Animation {
+    /// Starts a new animation at the same phase as another animation.
+    /// The frame position is scaled if the animations have different frame counts.
+    pub fn with_phase(def: Rc<AnimationDef>, from: &Animation) -> Animation {
+        let from_frame = from.get_current_frame();
+        let from_duration = from.def.durations[from_frame];
+        let current_frame = from_frame * def.frames.len() / from.frame_count();
+        let elapsed_time = from.elapsed_time / from_duration * def.durations[current_frame];
         Animation {
-            tileset_image: |a| &a.player_walk_tiles,
-            frame_count: 8,
-            frame_duration: 0.05,
-            current_frame: 0,
-            elapsed_time: 0.0,
-            loop_animation: true,
+            def,
+            current_frame,
+            elapsed_time,
+            speed: from.speed,
+            started: true,
         }
     }
 
-    pub fn player_running() -> Animation {
-        Animation {
-            tileset_image: |a| &a.player_run_tiles,
-            frame_count: 8,
-            frame_duration: 0.05,
-            current_frame: 0,
-            elapsed_time: 0.0,
-            loop_animation: true,
-        }
+    /// Sets the playback speed multiplier. Negative values are treated as zero.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.max(0.0);
     }
 
-    pub fn player_jumping() -> Animation {
-        Animation {
-            tileset_image: |a| &a.player_jump_tiles,
-            frame_count: 8,
-            frame_duration: 0.05,
-            current_frame: 0,
-            elapsed_time: 0.0,
-            loop_animation: false,
+    /// Update the animation based on elapsed time.
+    /// Returns the events of the frames that were entered during this update, in order.
+    /// Animations that complete also return `COMPLETE_EVENT` when they finish.
+    pub fn update(&mut self, elapsed: f32) -> Vec<Rc<str>> {
+        let mut events = Vec::new();
+        if !self.started {
+            self.started = true;
+            self.push_events(self.get_current_frame(), &mut events);
         }
+        self.elapsed_time += elapsed * self.speed;
+        while !self.is_finished() {
+            let duration = self.def.durations[self.get_current_frame()];
+            if self.elapsed_time < duration {
+                break;
+            }
+            self.current_frame += 1;
+            self.elapsed_time -= duration;
+            if self.is_finished() {
+                events.push(Rc::from(COMPLETE_EVENT));
+            } else {
+                self.push_events(self.get_current_frame(), &mut events);
+            }
+        }
+        events
     }
 
-    /// Update the animation based on elapsed time.
-    pub fn update(&mut self, elapsed: f32) {
-        self.elapsed_time += elapsed;
-        while self.elapsed_time >= self.frame_duration {
-            self.current_frame += 1;
-            self.elapsed_time -= self.frame_duration
+    fn push_events(&self, frame: usize, events: &mut Vec<Rc<str>>) {
+        for (_, name) in self.def.events.iter().filter(|(f, _)| *f == frame) {
+            events.push(name.clone());
         }
     }
 
+    /// Returns true if a completing animation has played all of its frames
+    pub fn is_finished(&self) -> bool {
+        self.def.mode.completes() && self.current_frame >= self.frame_count()
+    }
+
     /// Return the current frame position
     pub fn get_current_frame(&self) -> usize {
-        if self.loop_animation {
-            self.current_frame % self.frame_count
-        } else {
-            min(self.frame_count - 1, self.current_frame)
+        let count = self.frame_count();
+        match self.def.mode {
+            PlaybackMode::Loop => self.current_frame % count,
+            PlaybackMode::Once if self.current_frame >= count => 0,
+            PlaybackMode::Once => self.current_frame,
+            PlaybackMode::OnceHold => min(count - 1, self.current_frame),
+            PlaybackMode::PingPong if count == 1 => 0,
+            PlaybackMode::PingPong => {
+                let period = 2 * count - 2;
+                let pos = self.current_frame % period;
+                if pos < count {
+                    pos
+                } else {
+                    period - pos
+                }
+            }
+            PlaybackMode::Reverse => count - 1 - self.current_frame % count,
         }
     }
+
+    /// Return the index of the current frame in the sprite sheet
+    pub fn get_sheet_frame(&self) -> usize {
+        self.def.frames[self.get_current_frame()]
+    }
+
+    fn frame_count(&self) -> usize {
+        self.def.frames.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn animation(mode: PlaybackMode, frames: usize) -> Animation {
+        Animation::new(Rc::new(AnimationDef {
+            name: String::from("test"),
+            sheet: String::from("/test.png"),
+            frame_size: Dimensions::new(16.0, 16.0),
+            frames: (0..frames).collect(),
+            durations: vec![1.0; frames],
+            mode,
+            events: vec![(1, Rc::from("second"))],
+        }))
+    }
+
+    /// Returns the frame positions shown after each one second step
+    fn play(animation: &mut Animation, steps: usize) -> Vec<usize> {
+        let mut frames = vec![animation.get_current_frame()];
+        for _ in 0..steps {
+            animation.update(1.0);
+            frames.push(animation.get_current_frame());
+        }
+        frames
+    }
+
+    #[test]
+    fn loop_mode_wraps_around() {
+        let mut a = animation(PlaybackMode::Loop, 3);
+        assert_eq!(play(&mut a, 7), vec![0, 1, 2, 0, 1, 2, 0, 1]);
+        assert!(!a.is_finished());
+    }
+
+    #[test]
+    fn once_mode_returns_to_first_frame() {
+        let mut a = animation(PlaybackMode::Once, 3);
+        assert_eq!(play(&mut a, 5), vec![0, 1, 2, 0, 0, 0]);
+        assert!(a.is_finished());
+    }
+
+    #[test]
+    fn hold_mode_holds_last_frame() {
+        let mut a = animation(PlaybackMode::OnceHold, 3);
+        assert_eq!(play(&mut a, 5), vec![0, 1, 2, 2, 2, 2]);
+        assert!(a.is_finished());
+    }
+
+    #[test]
+    fn ping_pong_mode_plays_back_and_forth() {
+        let mut a = animation(PlaybackMode::PingPong, 3);
+        assert_eq!(play(&mut a, 8), vec![0, 1, 2, 1, 0, 1, 2, 1, 0]);
+        assert!(!a.is_finished());
+    }
+
+    #[test]
+    fn ping_pong_mode_with_single_frame() {
+        let mut a = animation(PlaybackMode::PingPong, 1);
+        assert_eq!(play(&mut a, 3), vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn reverse_mode_plays_backwards() {
+        let mut a = animation(PlaybackMode::Reverse, 3);
+        assert_eq!(play(&mut a, 5), vec![2, 1, 0, 2, 1, 0]);
+        assert!(!a.is_finished());
+    }
+
+    #[test]
+    fn completing_modes_emit_complete_event_once() {
+        for mode in [PlaybackMode::Once, PlaybackMode::OnceHold] {
+            let mut a = animation(mode, 2);
+            assert_eq!(a.update(1.0), vec![Rc::from("second")]);
+            assert_eq!(a.update(1.0), vec![Rc::from(COMPLETE_EVENT)]);
+            assert!(a.update(1.0).is_empty());
+        }
+    }
+
+    #[test]
+    fn looping_modes_do_not_complete() {
+        let mut a = animation(PlaybackMode::Loop, 2);
+        let events: Vec<Rc<str>> = (0..6).flat_map(|_| a.update(1.0)).collect();
+        assert_eq!(events, vec![Rc::from("second"); 3]);
+    }
+
+    #[test]
+    fn speed_scales_elapsed_time() {
+        let mut a = animation(PlaybackMode::Loop, 4);
+        a.set_speed(2.0);
+        a.update(1.0);
+        assert_eq!(a.get_current_frame(), 2);
+        a.set_speed(0.5);
+        a.update(1.0);
+        assert_eq!(a.get_current_frame(), 2);
+        a.update(1.0);
+        assert_eq!(a.get_current_frame(), 3);
+    }
 }
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::animation_handler::{Animation, AnimationDef};
+
+/// How to switch from one animation to another.
+#[derive(Debug, Clone)]
+pub enum Transition {
+    /// Continue the new animation from the same phase of the cycle.
+    KeepPhase,
+    /// Play a transition clip before starting the new animation.
+    Clip(Rc<AnimationDef>),
+}
+
+/// Transition rules, by (from animation, to animation) names.
+pub type Transitions = HashMap<(String, String), Transition>;
+
+/// Plays a character's animations, applying transition rules when the animation changes.
+#[derive(Debug)]
+pub struct Animator {
+    current: Animation,
+    /// Animation to start when the current transition clip has finished
+    queued: Option<Rc<AnimationDef>>,
+    transitions: Rc<Transitions>,
+}
+
+impl Animator {
+    pub fn new(def: Rc<AnimationDef>, transitions: Rc<Transitions>) -> Animator {
+        Animator {
+            current: Animation::new(def),
+            queued: None,
+            transitions,
+        }
+    }
+
+    /// The animation that is currently shown
+    pub fn current(&self) -> &Animation {
+        &self.current
+    }
+
+    pub fn current_mut(&mut self) -> &mut Animation {
+        &mut self.current
+    }
+
+    /// Name of the animation being played, or the one that starts after the transition clip.
+    pub fn target(&self) -> &str {
+        match &self.queued {
+            Some(def) => &def.name,
+            None => &self.current.def.name,
+        }
+    }
+
+    /// Switches to the given animation, using the transition rule from
+    /// the current animation if there is one.
+    pub fn play(&mut self, def: Rc<AnimationDef>) {
+        let key = (self.target().to_string(), def.name.clone());
+        match self.transitions.get(&key) {
+            // Phase of a transition clip has no meaning for the target animation
+            Some(Transition::KeepPhase) if self.queued.is_none() => {
+                self.current = Animation::with_phase(def, &self.current);
+            }
+            Some(Transition::Clip(clip)) => {
+                self.current = Animation::new(clip.clone());
+                self.queued = Some(def);
+                return;
+            }
+            _ => self.current = Animation::new(def),
+        }
+        self.queued = None;
+    }
+
+    /// Updates the current animation, and starts the queued animation when
+    /// a transition clip finishes. Returns the animation events of this update.
+    pub fn update(&mut self, elapsed: f32) -> Vec<Rc<str>> {
+        let mut events = self.current.update(elapsed);
+        if self.current.is_finished() {
+            if let Some(next) = self.queued.take() {
+                self.current = Animation::new(next);
+                events.extend(self.current.update(0.0));
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation_handler::PlaybackMode;
+    use crate::primitives::Dimensions;
+
+    fn def(name: &str, frames: usize, mode: PlaybackMode) -> Rc<AnimationDef> {
+        Rc::new(AnimationDef {
+            name: name.to_string(),
+            sheet: String::from("/test.png"),
+            frame_size: Dimensions::new(16.0, 16.0),
+            frames: (0..frames).collect(),
+            durations: vec![1.0; frames],
+            mode,
+            events: Vec::new(),
+        })
+    }
+
+    fn transitions(rules: Vec<(&str, &str, Transition)>) -> Rc<Transitions> {
+        Rc::new(
+            rules
+                .into_iter()
+                .map(|(from, to, t)| ((from.to_string(), to.to_string()), t))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn keep_phase_continues_from_same_frame() {
+        let walk = def("walk", 8, PlaybackMode::Loop);
+        let run = def("run", 8, PlaybackMode::Loop);
+        let rules = transitions(vec![("walk", "run", Transition::KeepPhase)]);
+        let mut animator = Animator::new(walk, rules);
+        animator.update(3.5);
+        animator.play(run);
+        assert_eq!(animator.current().def.name, "run");
+        assert_eq!(animator.current().get_current_frame(), 3);
+        animator.update(0.5);
+        assert_eq!(animator.current().get_current_frame(), 4);
+    }
+
+    #[test]
+    fn keep_phase_scales_frame_position() {
+        let walk = def("walk", 8, PlaybackMode::Loop);
+        let run = def("run", 4, PlaybackMode::Loop);
+        let rules = transitions(vec![("walk", "run", Transition::KeepPhase)]);
+        let mut animator = Animator::new(walk, rules);
+        animator.update(6.0);
+        animator.play(run);
+        assert_eq!(animator.current().get_current_frame(), 3);
+    }
+
+    #[test]
+    fn clip_plays_before_target() {
+        let walk = def("walk", 8, PlaybackMode::Loop);
+        let stop = def("stop", 2, PlaybackMode::OnceHold);
+        let idle = def("idle", 4, PlaybackMode::Loop);
+        let rules = transitions(vec![("walk", "idle", Transition::Clip(stop))]);
+        let mut animator = Animator::new(walk, rules);
+        animator.play(idle);
+        assert_eq!(animator.current().def.name, "stop");
+        assert_eq!(animator.target(), "idle");
+        animator.update(1.0);
+        assert_eq!(animator.current().def.name, "stop");
+        animator.update(1.0);
+        assert_eq!(animator.current().def.name, "idle");
+        assert_eq!(animator.current().get_current_frame(), 0);
+    }
+
+    #[test]
+    fn no_rule_restarts_animation() {
+        let walk = def("walk", 8, PlaybackMode::Loop);
+        let jump = def("jump", 4, PlaybackMode::OnceHold);
+        let mut animator = Animator::new(walk, transitions(Vec::new()));
+        animator.update(3.0);
+        animator.play(jump);
+        assert_eq!(animator.current().def.name, "jump");
+        assert_eq!(animator.current().get_current_frame(), 0);
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use ggez::graphics::{BlendMode, Canvas, Color, DrawParam, Image, ImageFormat, Rect, Sampler};
+use ggez::{Context, GameResult};
+
+use crate::primitives::Point2;
+
+/// Maximum width of the atlas image, in pixels. Wider images get a row of their own.
+const MAX_ATLAS_WIDTH: u32 = 2048;
+/// Empty space around packed images, so that filtering does not bleed between them.
+const PADDING: u32 = 2;
+
+/// Sprite sheets packed into a single image, so that all sprites can be drawn
+/// without switching textures.
+pub struct TextureAtlas {
+    pub image: Image,
+    /// Position of each packed image in the atlas, by path, in pixels
+    regions: HashMap<String, Rect>,
+}
+
+impl TextureAtlas {
+    /// Loads the images at the given paths and draws them into one atlas image.
+    pub fn build<'a>(
+        ctx: &mut Context,
+        paths: impl IntoIterator<Item = &'a str>,
+    ) -> GameResult<TextureAtlas> {
+        let mut images: Vec<(String, Image)> = Vec::new();
+        for path in paths {
+            if !images.iter().any(|(p, _)| p == path) {
+                images.push((path.to_string(), Image::from_path(ctx, path)?));
+            }
+        }
+
+        let sizes: Vec<_> = images
+            .iter()
+            .map(|(_, img)| (img.width(), img.height()))
+            .collect();
+        let (width, height, positions) = pack(&sizes, MAX_ATLAS_WIDTH);
+
+        let image = Image::new_canvas_image(ctx, ImageFormat::Rgba8UnormSrgb, width, height, 1);
+        let mut canvas = Canvas::from_image(ctx, image.clone(), Color::new(0.0, 0.0, 0.0, 0.0));
+        // Copy the pixels as they are
+        canvas.set_sampler(Sampler::nearest_clamp());
+        canvas.set_blend_mode(BlendMode::REPLACE);
+
+        let mut regions = HashMap::new();
+        for ((path, img), pos) in images.iter().zip(positions) {
+            canvas.draw(img, DrawParam::new().dest(pos));
+            let region = Rect::new(pos.x, pos.y, img.width() as f32, img.height() as f32);
+            regions.insert(path.clone(), region);
+        }
+        canvas.finish(ctx)?;
+
+        Ok(TextureAtlas { image, regions })
+    }
+
+    /// Returns the position of a packed image in the atlas, in pixels.
+    pub fn region(&self, path: &str) -> Rect {
+        *self
+            .regions
+            .get(path)
+            .unwrap_or_else(|| panic!("Image {} is not in the texture atlas", path))
+    }
+
+    /// Converts a rectangle in pixels, relative to the upper left corner of a packed image,
+    /// to a draw param source rect in the atlas.
+    pub fn source_rect(&self, path: &str, rect: Rect) -> Rect {
+        let region = self.region(path);
+        let width = self.image.width() as f32;
+        let height = self.image.height() as f32;
+        Rect {
+            x: (region.x + rect.x) / width,
+            y: (region.y + rect.y) / height,
+            w: rect.w / width,
+            h: rect.h / height,
+        }
+    }
+}
+
+/// Packs rectangles of the given sizes into rows, tallest first.
+/// Returns the size of the packed area and the position of each rectangle, in the given order.
+pub fn pack(sizes: &[(u32, u32)], max_width: u32) -> (u32, u32, Vec<Point2>) {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(sizes[i].1));
+
+    let mut positions = vec![Point2::new(0.0, 0.0); sizes.len()];
+    let (mut x, mut y) = (PADDING, PADDING);
+    let mut row_height = 0;
+    let mut width = 0;
+    for i in order {
+        let (w, h) = sizes[i];
+        if x > PADDING && x + w + PADDING > max_width {
+            x = PADDING;
+            y += row_height + PADDING;
+            row_height = 0;
+        }
+        positions[i] = Point2::new(x as f32, y as f32);
+        x += w + PADDING;
+        width = width.max(x);
+        row_height = row_height.max(h);
+    }
+    (width.max(1), (y + row_height + PADDING).max(1), positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlaps(a: (Point2, (u32, u32)), b: (Point2, (u32, u32))) -> bool {
+        let ra = Rect::new(a.0.x, a.0.y, a.1 .0 as f32, a.1 .1 as f32);
+        let rb = Rect::new(b.0.x, b.0.y, b.1 .0 as f32, b.1 .1 as f32);
+        ra.overlaps(&rb)
+    }
+
+    #[test]
+    fn packed_images_do_not_overlap() {
+        let sizes = [(1024, 128), (384, 256), (1024, 128), (1024, 128), (64, 64)];
+        let (width, height, positions) = pack(&sizes, 2048);
+        assert!(width <= 2048);
+        for i in 0..sizes.len() {
+            let p = positions[i];
+            assert!(p.x as u32 + sizes[i].0 <= width);
+            assert!(p.y as u32 + sizes[i].1 <= height);
+            for j in i + 1..sizes.len() {
+                assert!(!overlaps((p, sizes[i]), (positions[j], sizes[j])));
+            }
+        }
+    }
+
+    #[test]
+    fn starts_new_row_when_full() {
+        let (width, height, positions) = pack(&[(100, 50), (100, 40)], 150);
+        assert_eq!(positions[0], Point2::new(2.0, 2.0));
+        assert_eq!(positions[1], Point2::new(2.0, 54.0));
+        assert_eq!((width, height), (104, 96));
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::BufRead;
+
+use ggez::audio::{SoundSource, Source};
+use ggez::{Context, GameError, GameResult};
+
+/// Time it takes for a new music track to fully replace the previous one, in seconds.
+const CROSSFADE_TIME: f32 = 1.5;
+
+/// Sound effect names, as defined in the sound data file
+pub const JUMP_SOUND: &str = "jump";
+pub const LAND_SOUND: &str = "land";
+pub const FOOTSTEP_SOUND: &str = "footstep";
+
+/// Volume levels, from 0 to 1. Music and sound effect volumes are scaled by the master volume.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Volumes {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+}
+
+impl Default for Volumes {
+    fn default() -> Self {
+        Volumes {
+            master: 1.0,
+            music: 0.6,
+            sfx: 0.8,
+        }
+    }
+}
+
+/// Plays sounds and music tracks by name. Unknown names are ignored.
+pub trait AudioBackend {
+    /// Plays a sound effect once.
+    fn play_sound(&mut self, name: &str, volume: f32);
+    /// Plays a looping music track at the given volume, resuming it if it was paused.
+    fn play_music(&mut self, name: &str, volume: f32);
+    fn pause_music(&mut self, name: &str);
+}
+
+/// Plays audio through ggez, with sounds loaded from a data file.
+pub struct GgezAudio {
+    sounds: HashMap<String, Source>,
+    music: HashMap<String, Source>,
+}
+
+impl GgezAudio {
+    /// Loads the sounds and music tracks listed in a data file. Missing audio files
+    /// are skipped with a warning, so the game can be played without them.
+    pub fn load(ctx: &Context, file: &str) -> GameResult<GgezAudio> {
+        let reader = io::BufReader::new(ctx.fs.open(file)?);
+        let mut audio = GgezAudio {
+            sounds: HashMap::new(),
+            music: HashMap::new(),
+        };
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (is_music, name, path) = match fields[..] {
+                ["sound", name, path] => (false, name, path),
+                ["music", name, path] => (true, name, path),
+                _ => {
+                    return Err(GameError::ResourceLoadError(format!(
+                        "{}:{}: expected sound or music, name and path",
+                        file,
+                        index + 1
+                    )))
+                }
+            };
+            if !ctx.fs.exists(path) {
+                println!("Audio file {} not found, {} will be silent", path, name);
+                continue;
+            }
+            let mut source = Source::new(ctx, path)?;
+            source.set_repeat(is_music);
+            let sources = match is_music {
+                true => &mut audio.music,
+                false => &mut audio.sounds,
+            };
+            sources.insert(name.to_string(), source);
+        }
+        Ok(audio)
+    }
+}
+
+impl AudioBackend for GgezAudio {
+    fn play_sound(&mut self, name: &str, volume: f32) {
+        if let Some(source) = self.sounds.get_mut(name) {
+            source.set_volume(volume);
+            if let Err(e) = source.play_later() {
+                println!("Could not play sound {}: {}", name, e);
+            }
+        }
+    }
+
+    fn play_music(&mut self, name: &str, volume: f32) {
+        if let Some(source) = self.music.get_mut(name) {
+            source.set_volume(volume);
+            if source.paused() {
+                source.resume();
+            } else if !source.playing() {
+                if let Err(e) = source.play_later() {
+                    println!("Could not play music {}: {}", name, e);
+                }
+            }
+        }
+    }
+
+    fn pause_music(&mut self, name: &str) {
+        if let Some(source) = self.music.get(name) {
+            source.pause();
+        }
+    }
+}
+
+/// Silent backend, for running without an audio device.
+/// Keeps track of what would have been played.
+#[derive(Debug, Default)]
+pub struct NullAudio {
+    pub played_sounds: Vec<String>,
+    /// Volume of each music track that is playing
+    pub music: HashMap<String, f32>,
+}
+
+impl AudioBackend for NullAudio {
+    fn play_sound(&mut self, name: &str, _volume: f32) {
+        self.played_sounds.push(name.to_string());
+    }
+
+    fn play_music(&mut self, name: &str, volume: f32) {
+        self.music.insert(name.to_string(), volume);
+    }
+
+    fn pause_music(&mut self, name: &str) {
+        self.music.remove(name);
+    }
+}
+
+/// Music track and how far it has faded in, from 0 to 1.
+#[derive(Debug)]
+struct Track {
+    name: String,
+    fade: f32,
+}
+
+/// Plays sound effects and music at the configured volumes,
+/// crossfading between music tracks.
+pub struct AudioManager {
+    backend: Box<dyn AudioBackend>,
+    volumes: Volumes,
+    /// Track that is playing or fading in
+    music: Option<Track>,
+    /// Previous tracks that are fading out
+    fading_out: Vec<Track>,
+}
+
+impl AudioManager {
+    pub fn new(backend: Box<dyn AudioBackend>) -> AudioManager {
+        AudioManager {
+            backend,
+            volumes: Volumes::default(),
+            music: None,
+            fading_out: Vec::new(),
+        }
+    }
+
+    pub fn set_volumes(&mut self, volumes: Volumes) {
+        self.volumes = volumes;
+        self.update_music_volumes();
+    }
+
+    pub fn play_sound(&mut self, name: &str) {
+        let volume = self.volumes.master * self.volumes.sfx;
+        if volume > 0.0 {
+            self.backend.play_sound(name, volume);
+        }
+    }
+
+    /// Switches to another music track, crossfading from the current one.
+    /// Does nothing if the track is already playing.
+    pub fn play_music(&mut self, name: &str) {
+        if self.music.as_ref().is_some_and(|t| t.name == name) {
+            return;
+        }
+        // A track that is fading out fades back in from where it is
+        let fade = match self.fading_out.iter().position(|t| t.name == name) {
+            Some(index) => self.fading_out.remove(index).fade,
+            None => 0.0,
+        };
+        if let Some(previous) = self.music.take() {
+            self.fading_out.push(previous);
+        }
+        self.music = Some(Track {
+            name: name.to_string(),
+            fade,
+        });
+        self.update_music_volumes();
+    }
+
+    /// Advances music crossfades.
+    pub fn update(&mut self, seconds: f32) {
+        let step = seconds / CROSSFADE_TIME;
+        if let Some(track) = &mut self.music {
+            track.fade = (track.fade + step).min(1.0);
+        }
+        for track in &mut self.fading_out {
+            track.fade = (track.fade - step).max(0.0);
+        }
+        self.update_music_volumes();
+        for track in self.fading_out.iter().filter(|t| t.fade <= 0.0) {
+            self.backend.pause_music(&track.name);
+        }
+        self.fading_out.retain(|t| t.fade > 0.0);
+    }
+
+    fn update_music_volumes(&mut self) {
+        let volume = self.volumes.master * self.volumes.music;
+        for track in self.music.iter().chain(&self.fading_out) {
+            self.backend.play_music(&track.name, volume * track.fade);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Null backend that can be inspected after it has been given to the manager.
+    #[derive(Default, Clone)]
+    struct SharedNull(Rc<RefCell<NullAudio>>);
+
+    impl AudioBackend for SharedNull {
+        fn play_sound(&mut self, name: &str, volume: f32) {
+            self.0.borrow_mut().play_sound(name, volume)
+        }
+
+        fn play_music(&mut self, name: &str, volume: f32) {
+            self.0.borrow_mut().play_music(name, volume)
+        }
+
+        fn pause_music(&mut self, name: &str) {
+            self.0.borrow_mut().pause_music(name)
+        }
+    }
+
+    fn manager() -> (AudioManager, SharedNull) {
+        let backend = SharedNull::default();
+        let volumes = Volumes {
+            master: 1.0,
+            music: 1.0,
+            sfx: 1.0,
+        };
+        let mut audio = AudioManager::new(Box::new(backend.clone()));
+        audio.set_volumes(volumes);
+        (audio, backend)
+    }
+
+    #[test]
+    fn crossfades_between_tracks() {
+        let (mut audio, backend) = manager();
+        audio.play_music("title");
+        audio.update(CROSSFADE_TIME);
+        assert_eq!(backend.0.borrow().music["title"], 1.0);
+
+        audio.play_music("level");
+        audio.update(CROSSFADE_TIME / 2.0);
+        assert_eq!(backend.0.borrow().music["title"], 0.5);
+        assert_eq!(backend.0.borrow().music["level"], 0.5);
+
+        audio.update(CROSSFADE_TIME / 2.0);
+        assert!(!backend.0.borrow().music.contains_key("title"));
+        assert_eq!(backend.0.borrow().music["level"], 1.0);
+    }
+
+    #[test]
+    fn muted_sounds_are_not_played() {
+        let (mut audio, backend) = manager();
+        audio.play_sound(JUMP_SOUND);
+        audio.set_volumes(Volumes {
+            master: 0.0,
+            ..Volumes::default()
+        });
+        audio.play_sound(LAND_SOUND);
+        assert_eq!(backend.0.borrow().played_sounds, vec![JUMP_SOUND]);
+    }
+}
@@ -0,0 +1,121 @@
+//! Picks ground tiles from the solid cells around them, so that level authors
+//! can draw solid ground with a single character.
+
+/// Level file character for solid ground whose tile is picked automatically
+pub const AUTOTILE_CHAR: char = 'X';
+
+/// Ground tile characters, indexed by the edges that have a border
+/// (bit flags: top 1, right 2, bottom 4, left 8).
+const EDGE_TILES: [char; 16] = [
+    '#', '^', '>', '┐', 'v', '=', '┘', '┤', '<', '┌', 'H', '┬', '└', '├', '┴', '┼',
+];
+const TOP: usize = 1;
+const RIGHT: usize = 2;
+const BOTTOM: usize = 4;
+const LEFT: usize = 8;
+
+/// Which neighbors of a cell are looked at when picking its tile.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Neighborhood {
+    /// The cells above, below and to the sides. A side has a border when the cell next to it is empty.
+    Four,
+    /// Also the diagonal cells: inner corners, where only a cell diagonally above is empty,
+    /// get a top border so that the ground's top edge continues around steps.
+    Eight,
+}
+
+/// Returns true for characters that are drawn as solid ground.
+fn is_ground(c: char) -> bool {
+    c == AUTOTILE_CHAR || EDGE_TILES.contains(&c)
+}
+
+/// Picks tiles for the ground cells of a level, given as rows of level file characters,
+/// top row first. Only `AUTOTILE_CHAR` cells are changed, unless `retile` is set,
+/// in which case all ground tiles are picked again. Cells outside the level count as solid,
+/// so the ground has no border at the level edges.
+pub fn autotile(
+    rows: &[String],
+    width: usize,
+    neighborhood: Neighborhood,
+    retile: bool,
+) -> Vec<String> {
+    let cells: Vec<Vec<char>> = rows.iter().map(|r| r.chars().collect()).collect();
+    let solid = |x: isize, y: isize| {
+        if x < 0 || y < 0 || x >= width as isize || y >= cells.len() as isize {
+            return true;
+        }
+        cells[y as usize]
+            .get(x as usize)
+            .is_some_and(|&c| is_ground(c))
+    };
+    let tile = |x: isize, y: isize| {
+        let mut edges = 0;
+        for (dx, dy, edge) in [(0, -1, TOP), (1, 0, RIGHT), (0, 1, BOTTOM), (-1, 0, LEFT)] {
+            if !solid(x + dx, y + dy) {
+                edges |= edge;
+            }
+        }
+        let inner_corner = !solid(x - 1, y - 1) || !solid(x + 1, y - 1);
+        if edges == 0 && neighborhood == Neighborhood::Eight && inner_corner {
+            edges = TOP;
+        }
+        EDGE_TILES[edges]
+    };
+    cells
+        .iter()
+        .enumerate()
+        .map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(
+                    |(x, &c)| match c == AUTOTILE_CHAR || (retile && is_ground(c)) {
+                        true => tile(x as isize, y as isize),
+                        false => c,
+                    },
+                )
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn picks_tiles_from_four_neighbors() {
+        let map = rows(&["      ", " XXX  ", " XXX X", "    XX"]);
+        let tiles = autotile(&map, 6, Neighborhood::Four, false);
+        assert_eq!(tiles, rows(&["      ", " ┌^┐  ", " └v┘ ┌", "    ┌#"]));
+    }
+
+    #[test]
+    fn keeps_other_characters_unless_retiling() {
+        let map = rows(&["o   ", "#X#B"]);
+        assert_eq!(
+            autotile(&map, 4, Neighborhood::Four, false),
+            rows(&["o   ", "#^#B"])
+        );
+        assert_eq!(
+            autotile(&map, 4, Neighborhood::Four, true),
+            rows(&["o   ", "^^┐B"])
+        );
+    }
+
+    #[test]
+    fn eight_neighbors_continue_top_edge_around_steps() {
+        let map = rows(&["X  ", "XXX"]);
+        assert_eq!(
+            autotile(&map, 3, Neighborhood::Four, false),
+            rows(&[">  ", "#^^"])
+        );
+        assert_eq!(
+            autotile(&map, 3, Neighborhood::Eight, false),
+            rows(&[">  ", "^^^"])
+        );
+    }
+}
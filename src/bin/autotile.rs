@@ -0,0 +1,67 @@
+//! Converts a plain level map, where solid ground is drawn with `X` or `#`,
+//! into the decorated level format with the ground tiles picked automatically.
+//!
+//! Usage: `autotile [--neighbors 4|8] <map file> [output file]`
+//! The result is printed if no output file is given.
+
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+use rust_sprite_game::autotile::{autotile, Neighborhood};
+
+const USAGE: &str = "Usage: autotile [--neighbors 4|8] <map file> [output file]";
+
+fn main() -> ExitCode {
+    let mut neighborhood = Neighborhood::Four;
+    let mut files = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--neighbors" => {
+                neighborhood = match args.next().as_deref() {
+                    Some("4") => Neighborhood::Four,
+                    Some("8") => Neighborhood::Eight,
+                    _ => return usage(),
+                }
+            }
+            _ => files.push(arg),
+        }
+    }
+    let (input, output) = match &files[..] {
+        [input] => (input, None),
+        [input, output] => (input, Some(output)),
+        _ => return usage(),
+    };
+
+    let text = match fs::read_to_string(input) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", input, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let rows: Vec<String> = text.lines().map(String::from).collect();
+    let width = rows.iter().map(|r| r.chars().count()).max().unwrap_or(0);
+    // Rows are padded to the same length, as the level check expects
+    let level: String = autotile(&rows, width, neighborhood, true)
+        .iter()
+        .map(|row| format!("{:1$}\n", row, width))
+        .collect();
+
+    match output {
+        Some(output) => {
+            if let Err(e) = fs::write(output, level) {
+                eprintln!("Could not write {}: {}", output, e);
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{}", level),
+    }
+    ExitCode::SUCCESS
+}
+
+fn usage() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::FAILURE
+}
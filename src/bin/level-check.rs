@@ -0,0 +1,38 @@
+//! Checks level files without starting the game, for example in CI.
+//!
+//! Usage: `level-check [level file...]`, checking `level.txt` if no files are given.
+//! Exits with status 1 if any problems were found, and 2 if a file could not be read.
+
+use std::env;
+use std::path::Path;
+use std::process::ExitCode;
+
+use rust_sprite_game::level_check::{check_level, TILESET_FILE};
+
+fn main() -> ExitCode {
+    let mut files: Vec<String> = env::args().skip(1).collect();
+    if files.is_empty() {
+        files.push(String::from("level.txt"));
+    }
+
+    let mut status = ExitCode::SUCCESS;
+    for file in &files {
+        let problems = match check_level(Path::new(file), Path::new(TILESET_FILE)) {
+            Ok(problems) => problems,
+            Err(e) => {
+                eprintln!("{}: could not read: {}", file, e);
+                return ExitCode::from(2);
+            }
+        };
+        for problem in &problems {
+            println!("{}:{}", file, problem);
+        }
+        if problems.is_empty() {
+            println!("{}: ok", file);
+        } else {
+            println!("{}: {} problem(s)", file, problems.len());
+            status = ExitCode::FAILURE;
+        }
+    }
+    status
+}
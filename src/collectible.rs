@@ -0,0 +1,89 @@
+use ggez::graphics::Rect;
+
+use crate::actor::Actor;
+use crate::constants::{COLLECTIBLE_SIZE, GROUND_TILE_HEIGHT, GROUND_TILE_WIDTH};
+use crate::entity::{Behavior, Entity, Shape, SpriteSource};
+use crate::primitives::{Dimensions, Direction, Point2};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CollectibleKind {
+    Coin,
+    Gem,
+}
+
+impl CollectibleKind {
+    /// Returns the collectible kind placed with the given level file character, if any.
+    pub fn for_char(char: char) -> Option<CollectibleKind> {
+        match char {
+            'o' => Some(CollectibleKind::Coin),
+            '*' => Some(CollectibleKind::Gem),
+            _ => None,
+        }
+    }
+
+    /// Score gained from collecting this item.
+    pub fn value(&self) -> u32 {
+        match self {
+            CollectibleKind::Coin => 10,
+            CollectibleKind::Gem => 50,
+        }
+    }
+}
+
+/// Creates a collectible entity centered on the given level tile.
+pub fn create_collectible(kind: CollectibleKind, x: usize, y: usize) -> Entity {
+    let pos = Point2::new(
+        x as f32 * GROUND_TILE_WIDTH + (GROUND_TILE_WIDTH - COLLECTIBLE_SIZE) / 2.0,
+        y as f32 * GROUND_TILE_HEIGHT + (GROUND_TILE_HEIGHT - COLLECTIBLE_SIZE) / 2.0,
+    );
+    let actor = Actor {
+        pos,
+        facing: Direction::Right,
+        sprite_size: Dimensions::new(COLLECTIBLE_SIZE, COLLECTIBLE_SIZE),
+        draw_offset: Point2::new(0.0, 0.0),
+        bbox: Rect {
+            x: pos.x,
+            y: pos.y,
+            w: COLLECTIBLE_SIZE,
+            h: COLLECTIBLE_SIZE,
+        },
+    };
+    let shape = match kind {
+        CollectibleKind::Coin => Shape::Coin,
+        CollectibleKind::Gem => Shape::Gem,
+    };
+    Entity::new(
+        actor,
+        SpriteSource::Shape(shape),
+        Behavior::Collectible(kind),
+    )
+}
+
+/// Score and collected item count for the current level.
+#[derive(Debug, Default)]
+pub struct Score {
+    pub score: u32,
+    pub collected: usize,
+    /// Total number of collectibles in the level
+    pub total: usize,
+}
+
+impl Score {
+    pub fn new(total: usize) -> Score {
+        Score {
+            score: 0,
+            collected: 0,
+            total,
+        }
+    }
+
+    pub fn add(&mut self, kind: CollectibleKind) {
+        self.score += kind.value();
+        self.collected += 1;
+    }
+
+    /// Returns true when all of the level's collectibles have been picked up.
+    pub fn is_complete(&self) -> bool {
+        self.total > 0 && self.collected == self.total
+    }
+}
@@ -34,3 +34,13 @@ pub fn find_mtv(target: &Rect, o: &Rect, velocity: Vec2, along_x: bool) -> Optio
         Some(Vec2::new(0.0, cy))
     }
 }
+
+/// A collision contact resolved during the last physics step.
+/// Used for debug visualization.
+#[derive(Debug, Copy, Clone)]
+pub struct Contact {
+    /// Center of the overlapping area, in game space.
+    pub point: Vec2,
+    /// Direction into which the colliding actor was pushed.
+    pub normal: Vec2,
+}
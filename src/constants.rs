@@ -4,13 +4,15 @@
 pub const SCREEN_WIDTH: f32 = 1280.0;
 pub const SCREEN_HEIGHT: f32 = 720.0;
 
-#[cfg(feature = "debug-bbox")]
-pub const DRAW_BBOX: bool = true;
+pub const DESIRED_FPS: u32 = 60;
 
-#[cfg(not(feature = "debug-bbox"))]
-pub const DRAW_BBOX: bool = false;
+/// Directory that game resources are loaded from, relative to the working directory
+pub const RESOURCE_DIR: &str = "resources";
 
-pub const DESIRED_FPS: u32 = 60;
+/// Level file, and the level size in tiles
+pub const LEVEL_FILE: &str = "level.txt";
+pub const LEVEL_WIDTH: usize = 40;
+pub const LEVEL_HEIGHT: usize = 23;
 
 pub const GROUND_TILE_WIDTH: f32 = 32.0;
 pub const GROUND_TILE_HEIGHT: f32 = 32.0;
@@ -20,3 +22,8 @@ pub const PLAYER_TILE_HEIGHT: f32 = 128.0;
 
 pub const PLAYER_BBOX_WIDTH: f32 = 42.0;
 pub const PLAYER_BBOX_HEIGHT: f32 = 74.0;
+
+pub const ATTACK_HITBOX_WIDTH: f32 = 48.0;
+pub const ATTACK_HITBOX_HEIGHT: f32 = 40.0;
+
+pub const COLLECTIBLE_SIZE: f32 = 16.0;
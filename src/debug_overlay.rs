@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawParam, Rect, Text};
+
+use crate::game::SpriteGame;
+use crate::primitives::Point2;
+
+/// Length of drawn contact normals, in game space pixels
+const CONTACT_NORMAL_LENGTH: f32 = 16.0;
+/// Velocity vectors are drawn scaled by this factor (pixels per second -> pixels)
+const VELOCITY_SCALE: f32 = 0.1;
+
+/// Runtime statistics shown on the debug overlay.
+/// The overlay itself is toggled with F3 (see `InputState::debug_overlay`).
+#[derive(Debug, Default)]
+pub struct DebugStats {
+    pub fps: f64,
+    /// Time taken by the last fixed-step game update
+    pub tick_time: Duration,
+}
+
+pub fn draw_debug_overlay(game: &SpriteGame, canvas: &mut Canvas) {
+    let gfx = &game.gfx;
+    gfx.draw_tile_grid(canvas);
+    game.traverse_entities(|e| gfx.draw_bbox(e, canvas));
+
+    let player = game.player_entity();
+    if let Some(hitbox) = game.player.attack_hitbox(player) {
+        gfx.draw_outline(&hitbox, Color::from_rgb(255, 140, 0), canvas);
+    }
+    for e in game.entities.iter() {
+        let Some(body) = &e.body else {
+            continue;
+        };
+        let bbox = &e.actor.bbox;
+        let center = Point2::new(bbox.x + bbox.w / 2.0, bbox.y + bbox.h / 2.0);
+        gfx.draw_vector(
+            center,
+            body.velocity * VELOCITY_SCALE,
+            Color::YELLOW,
+            canvas,
+        );
+        for c in &body.contacts {
+            gfx.draw_vector(
+                c.point,
+                c.normal * CONTACT_NORMAL_LENGTH,
+                Color::RED,
+                canvas,
+            );
+        }
+    }
+
+    let pos = player.actor.pos;
+    let (velocity, grounded) = match &player.body {
+        Some(body) => (body.velocity, body.grounded),
+        None => (Vec2::ZERO, false),
+    };
+    let info = format!(
+        "FPS: {:.1}\nTick: {:.3} ms\nState: {:?}\nGrounded: {}\nPos: {:.1}, {:.1}\nVelocity: {:.1}, {:.1}",
+        game.debug.fps,
+        game.debug.tick_time.as_secs_f64() * 1000.0,
+        game.player.state,
+        grounded,
+        pos.x,
+        pos.y,
+        velocity.x,
+        velocity.y,
+    );
+    let text = Text::new(info);
+    // Below the HUD's level name
+    let panel = Rect::new(8.0, 44.0, 220.0, 112.0);
+    gfx.draw_panel(panel, Color::new(0.0, 0.0, 0.0, 0.6), canvas);
+    canvas.draw(
+        &text,
+        DrawParam::new()
+            .dest(Point2::new(panel.x + 6.0, panel.y + 6.0))
+            .color(Color::WHITE),
+    );
+}
@@ -0,0 +1,206 @@
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawParam, Rect, Text, TextLayout};
+use ggez::input::keyboard::{KeyCode, KeyMods};
+use ggez::input::mouse::MouseButton;
+use ggez::Context;
+
+use crate::autotile::Neighborhood;
+use crate::constants::{GROUND_TILE_HEIGHT, GROUND_TILE_WIDTH, SCREEN_HEIGHT};
+use crate::game::SpriteGame;
+use crate::hud::{draw_text, HudAnchor};
+use crate::level_handler::LevelTiles;
+use crate::primitives::Point2;
+use crate::scene::{Scene, SceneAction};
+use crate::scenes::GameplayScene;
+
+/// Size of a palette slot, in screen pixels
+const SLOT_SIZE: f32 = 40.0;
+/// Space around the palette slots
+const PALETTE_MARGIN: f32 = 4.0;
+const PALETTE_COLOR: Color = Color::new(0.0, 0.0, 0.0, 0.7);
+const SELECTED_COLOR: Color = Color::new(1.0, 0.85, 0.2, 1.0);
+const CURSOR_COLOR: Color = Color::new(1.0, 0.85, 0.2, 1.0);
+
+/// Edits the level with the mouse: the left button places the selected palette item,
+/// and the right button erases. Pushed on top of the gameplay scene with F2.
+pub struct EditorScene {
+    tiles: LevelTiles,
+    /// Level file characters that can be placed
+    palette: Vec<char>,
+    selected: usize,
+    /// Mouse position in the game's virtual resolution
+    cursor: Option<Point2>,
+}
+
+impl EditorScene {
+    pub fn new() -> EditorScene {
+        let tiles = LevelTiles::new();
+        let palette = tiles.palette();
+        EditorScene {
+            tiles,
+            palette,
+            selected: 0,
+            cursor: None,
+        }
+    }
+
+    /// Palette slot area, in screen coordinates. The palette is drawn along the bottom of the screen.
+    fn slot_rect(index: usize) -> Rect {
+        Rect::new(
+            PALETTE_MARGIN + index as f32 * (SLOT_SIZE + PALETTE_MARGIN),
+            SCREEN_HEIGHT - SLOT_SIZE - PALETTE_MARGIN,
+            SLOT_SIZE,
+            SLOT_SIZE,
+        )
+    }
+
+    fn palette_rect(&self) -> Rect {
+        let last = Self::slot_rect(self.palette.len() - 1);
+        Rect::new(
+            0.0,
+            last.y - PALETTE_MARGIN,
+            last.right() + PALETTE_MARGIN,
+            SLOT_SIZE + 2.0 * PALETTE_MARGIN,
+        )
+    }
+
+    /// Returns the (x, y) indices of the level tile under the cursor.
+    fn cursor_tile(&self, game: &SpriteGame) -> Option<(usize, usize)> {
+        let cursor = self.cursor?;
+        if self.palette_rect().contains(cursor) {
+            return None;
+        }
+        game.level
+            .tile_at(Point2::new(cursor.x, SCREEN_HEIGHT - cursor.y))
+    }
+
+    /// Places a character in the level, and respawns the level's objects to show the change.
+    fn place(&mut self, game: &mut SpriteGame, x: usize, y: usize, char: char) {
+        if game.level.set_char(x, y, char) {
+            game.restart_level();
+        }
+    }
+
+    fn select(&mut self, step: isize) {
+        let count = self.palette.len() as isize;
+        self.selected = (self.selected as isize + step).rem_euclid(count) as usize;
+    }
+
+    fn save(&mut self, game: &mut SpriteGame) {
+        let path = game.level.path().to_path_buf();
+        match game.save_level() {
+            Ok(()) => println!("Saved level to {:?}", path),
+            Err(e) => println!("Could not save level to {:?}: {}", path, e),
+        }
+    }
+
+    fn draw_palette(&self, game: &SpriteGame, canvas: &mut Canvas) {
+        game.gfx
+            .draw_panel(self.palette_rect(), PALETTE_COLOR, canvas);
+        for (i, &char) in self.palette.iter().enumerate() {
+            let slot = Self::slot_rect(i);
+            if i == self.selected {
+                let frame = Rect::new(slot.x - 2.0, slot.y - 2.0, slot.w + 4.0, slot.h + 4.0);
+                game.gfx.draw_panel(frame, SELECTED_COLOR, canvas);
+            }
+            match self.tiles.get(char) {
+                Some(tile) => game.gfx.draw_tile_icon(&tile, slot, canvas),
+                // Objects are shown with their level file character
+                None => {
+                    game.gfx.draw_panel(slot, Color::BLACK, canvas);
+                    let mut text = Text::new(char);
+                    text.set_scale(SLOT_SIZE * 0.6)
+                        .set_layout(TextLayout::center());
+                    canvas.draw(
+                        &text,
+                        DrawParam::new().dest(slot.center()).color(Color::WHITE),
+                    );
+                }
+            }
+        }
+    }
+}
+
+impl Scene for EditorScene {
+    fn update(&mut self, game: &mut SpriteGame, ctx: &mut Context, _seconds: f32) -> SceneAction {
+        let mouse = ctx.mouse.position();
+        self.cursor = game.viewport.to_virtual(Vec2::new(mouse.x, mouse.y));
+        let Some(cursor) = self.cursor else {
+            return SceneAction::None;
+        };
+        let left = ctx.mouse.button_pressed(MouseButton::Left);
+        let right = ctx.mouse.button_pressed(MouseButton::Right);
+        if left {
+            let slot = (0..self.palette.len()).find(|&i| Self::slot_rect(i).contains(cursor));
+            if let Some(slot) = slot {
+                self.selected = slot;
+                return SceneAction::None;
+            }
+        }
+        if let Some((x, y)) = self.cursor_tile(game) {
+            if left {
+                self.place(game, x, y, self.palette[self.selected]);
+            } else if right {
+                self.place(game, x, y, ' ');
+            }
+        }
+        SceneAction::None
+    }
+
+    fn draw(&mut self, game: &mut SpriteGame, canvas: &mut Canvas) {
+        game.draw_frame(canvas);
+        game.gfx.draw_tile_grid(canvas);
+        if let Some((x, y)) = self.cursor_tile(game) {
+            let tile = Rect::new(
+                x as f32 * GROUND_TILE_WIDTH,
+                y as f32 * GROUND_TILE_HEIGHT,
+                GROUND_TILE_WIDTH,
+                GROUND_TILE_HEIGHT,
+            );
+            game.gfx.draw_outline(&tile, CURSOR_COLOR, canvas);
+        }
+        self.draw_palette(game, canvas);
+
+        let title = match game.level.is_modified() {
+            true => "Level editor (modified)",
+            false => "Level editor",
+        };
+        draw_text(title, HudAnchor::Left, 1, canvas);
+        let help = "Q/E: select   T: autotile   Ctrl+S: save   P: play from cursor   F2: exit";
+        draw_text(help, HudAnchor::Left, 2, canvas);
+    }
+
+    fn key_pressed(
+        &mut self,
+        game: &mut SpriteGame,
+        ctx: &mut Context,
+        key: KeyCode,
+    ) -> SceneAction {
+        match key {
+            KeyCode::Q => self.select(-1),
+            KeyCode::E => self.select(1),
+            KeyCode::S if ctx.keyboard.is_mod_active(KeyMods::CTRL) => self.save(game),
+            KeyCode::T => {
+                let neighborhood = match ctx.keyboard.is_mod_active(KeyMods::SHIFT) {
+                    true => Neighborhood::Eight,
+                    false => Neighborhood::Four,
+                };
+                game.level.autotile(neighborhood);
+                game.restart_level();
+            }
+            KeyCode::P => {
+                if let Some((x, y)) = self.cursor_tile(game) {
+                    game.restart_level();
+                    game.place_player(x, y);
+                    return SceneAction::Push(Box::new(GameplayScene::test_play()));
+                }
+            }
+            KeyCode::F2 | KeyCode::Escape => {
+                game.restart_level();
+                return SceneAction::Pop;
+            }
+            _ => {}
+        }
+        SceneAction::None
+    }
+}
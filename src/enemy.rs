@@ -0,0 +1,123 @@
+use ggez::graphics::Rect;
+
+use crate::actor::Actor;
+use crate::animation_handler::AnimationLibrary;
+use crate::constants::{GROUND_TILE_HEIGHT, GROUND_TILE_WIDTH};
+use crate::entity::{Behavior, Entity, SpriteSource};
+use crate::level_handler::LevelHandler;
+use crate::physics::PhysicsBody;
+use crate::primitives::{Direction, Point2};
+
+const ENEMY_WALK_ANIMATION: &str = "enemy_walk";
+const ENEMY_IDLE_ANIMATION: &str = "enemy_idle";
+const ENEMY_TURN_ANIMATION: &str = "enemy_turn";
+const ENEMY_HURT_ANIMATION: &str = "enemy_hurt";
+
+// Speeds are pixels per second
+const PATROL_SPEED: f32 = 90.0;
+/// How long an enemy stands still before turning around, in seconds
+const TURN_PAUSE: f32 = 0.6;
+
+/// What a patrolling enemy is doing. Each state has its own animation.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PatrolState {
+    Walking,
+    /// Standing at a wall or ledge, with the seconds left until the enemy turns around
+    Waiting(f32),
+    /// Turning around; the enemy walks on when the turn animation completes
+    Turning,
+    /// Hit by the player's attack; the enemy is removed when the hurt animation completes
+    Hurt,
+}
+
+/// Creates an enemy standing on the given level tile.
+/// The enemy walks back and forth, turning around at walls and ledges.
+pub fn create_enemy(x: usize, y: usize, animations: &AnimationLibrary) -> Entity {
+    let pos = Point2::new(x as f32 * GROUND_TILE_WIDTH, y as f32 * GROUND_TILE_HEIGHT);
+    Entity {
+        body: Some(PhysicsBody::new()),
+        ..Entity::new(
+            Actor::create_knight(pos, Direction::Left),
+            SpriteSource::Animation(animations.create(ENEMY_WALK_ANIMATION)),
+            Behavior::Patrol {
+                speed: PATROL_SPEED,
+                state: PatrolState::Walking,
+            },
+        )
+    }
+}
+
+/// Updates the state, walking direction and speed of a patrolling entity.
+/// Enemies stop at walls and ledges, wait for a moment, and turn around.
+pub fn patrol(
+    entity: &mut Entity,
+    seconds: f32,
+    level: &LevelHandler,
+    animations: &AnimationLibrary,
+) {
+    let (Behavior::Patrol { speed, state }, Some(body)) = (entity.behavior, &entity.body) else {
+        return;
+    };
+    let finished = entity.animation().is_some_and(|a| a.is_finished());
+    let next = match state {
+        PatrolState::Walking
+            if body.grounded && (body.hit_wall() || at_ledge(&entity.actor, level)) =>
+        {
+            PatrolState::Waiting(TURN_PAUSE)
+        }
+        PatrolState::Waiting(time) if time <= seconds => {
+            entity.actor.facing = entity.actor.facing.opposite();
+            PatrolState::Turning
+        }
+        PatrolState::Waiting(time) => PatrolState::Waiting(time - seconds),
+        PatrolState::Turning if finished => PatrolState::Walking,
+        PatrolState::Hurt if finished => {
+            entity.alive = false;
+            PatrolState::Hurt
+        }
+        state => state,
+    };
+    if std::mem::discriminant(&next) != std::mem::discriminant(&state) {
+        let animation = match next {
+            PatrolState::Walking => ENEMY_WALK_ANIMATION,
+            PatrolState::Waiting(_) => ENEMY_IDLE_ANIMATION,
+            PatrolState::Turning => ENEMY_TURN_ANIMATION,
+            PatrolState::Hurt => ENEMY_HURT_ANIMATION,
+        };
+        entity.play_animation(animations.get(animation));
+    }
+    entity.behavior = Behavior::Patrol { speed, state: next };
+    if let Some(body) = &mut entity.body {
+        body.velocity.x = match next {
+            PatrolState::Walking => entity.actor.facing.mult() * speed,
+            _ => 0.0,
+        };
+    }
+}
+
+/// Stops a patrolling entity and plays its hurt animation, after which it is removed.
+pub fn hurt(entity: &mut Entity, animations: &AnimationLibrary) {
+    if let Behavior::Patrol { speed, .. } = entity.behavior {
+        entity.behavior = Behavior::Patrol {
+            speed,
+            state: PatrolState::Hurt,
+        };
+        entity.play_animation(animations.get(ENEMY_HURT_ANIMATION));
+    }
+}
+
+/// Checks whether there is no ground right in front of the actor.
+fn at_ledge(actor: &Actor, level: &LevelHandler) -> bool {
+    let bbox = &actor.bbox;
+    let x = match actor.facing {
+        Direction::Left => bbox.x - 1.0,
+        Direction::Right => bbox.x + bbox.w,
+    };
+    let ground_check = Rect {
+        x,
+        y: bbox.y - 1.0,
+        w: 1.0,
+        h: 1.0,
+    };
+    !level.collides_with(&ground_check)
+}
@@ -0,0 +1,260 @@
+use std::rc::Rc;
+
+use ggez::graphics::{Color, Rect};
+
+use crate::actor::Actor;
+use crate::animation_handler::{Animation, AnimationDef};
+use crate::animator::Animator;
+use crate::atlas::TextureAtlas;
+use crate::collectible::CollectibleKind;
+use crate::enemy::PatrolState;
+use crate::game_assets::GROUND_TILESET;
+use crate::physics::PhysicsBody;
+
+/// Index of an entity in the `EntityStore`.
+pub type EntityId = usize;
+
+/// Describes where an entity's sprite is drawn from.
+#[derive(Debug)]
+pub enum SpriteSource {
+    /// A tile in the ground tileset. x and y are tile indices from the upper left corner.
+    Tile { x: usize, y: usize },
+    /// Current frame of an animation.
+    Animation(Animator),
+    /// A shape that is drawn with a mesh instead of an image.
+    Shape(Shape),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Shape {
+    Coin,
+    Gem,
+}
+
+/// Draw order of the things on screen, back to front.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RenderLayer {
+    /// Parallax background layers
+    Background,
+    /// Solid level tiles
+    Terrain,
+    /// Decoration tiles behind the player; also enemies and pickups
+    Decoration,
+    Player,
+    /// Decoration tiles in front of the player, such as hanging moss
+    Foreground,
+    Hud,
+}
+
+impl RenderLayer {
+    /// All layers, in draw order.
+    pub const ALL: [RenderLayer; 6] = [
+        RenderLayer::Background,
+        RenderLayer::Terrain,
+        RenderLayer::Decoration,
+        RenderLayer::Player,
+        RenderLayer::Foreground,
+        RenderLayer::Hud,
+    ];
+}
+
+/// Describes how an entity acts in the game world.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Behavior {
+    /// Static level geometry.
+    Solid,
+    /// Level geometry that can be destroyed by attacking it.
+    Breakable,
+    /// Level tiles that are only drawn; nothing collides with them.
+    Decoration,
+    /// Controlled by the player's input.
+    Player,
+    /// Walks back and forth, turning around at walls and ledges.
+    /// Damages the player on contact.
+    Patrol { speed: f32, state: PatrolState },
+    /// Picked up by the player on contact.
+    Collectible(CollectibleKind),
+}
+
+impl Behavior {
+    /// Returns true if touching this entity damages the player.
+    pub fn is_hostile(&self) -> bool {
+        matches!(self, Behavior::Patrol { state, .. } if *state != PatrolState::Hurt)
+    }
+
+    /// Returns true if this entity is destroyed by the player's attack.
+    pub fn is_attackable(&self) -> bool {
+        match self {
+            Behavior::Patrol { state, .. } => *state != PatrolState::Hurt,
+            Behavior::Breakable => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if this entity blocks movement.
+    pub fn is_solid(&self) -> bool {
+        matches!(self, Behavior::Solid | Behavior::Breakable)
+    }
+
+    /// Layer that entities with this behavior are drawn on by default.
+    pub fn render_layer(&self) -> RenderLayer {
+        match self {
+            Behavior::Solid | Behavior::Breakable => RenderLayer::Terrain,
+            Behavior::Player => RenderLayer::Player,
+            Behavior::Decoration | Behavior::Patrol { .. } | Behavior::Collectible(_) => {
+                RenderLayer::Decoration
+            }
+        }
+    }
+
+    /// Bounding box color on the debug overlay.
+    pub fn debug_color(&self) -> Color {
+        match self {
+            Behavior::Solid => Color::BLUE,
+            Behavior::Breakable => Color::MAGENTA,
+            Behavior::Decoration => Color::CYAN,
+            Behavior::Player => Color::GREEN,
+            Behavior::Patrol { .. } => Color::RED,
+            Behavior::Collectible(_) => Color::YELLOW,
+        }
+    }
+}
+
+/// Anything that exists in the game world: level tiles, the player, enemies and pickups.
+#[derive(Debug)]
+pub struct Entity {
+    pub actor: Actor,
+    pub sprite: SpriteSource,
+    /// Physics state, for entities that move and are affected by gravity.
+    pub body: Option<PhysicsBody>,
+    pub behavior: Behavior,
+    pub layer: RenderLayer,
+    /// Color the sprite is multiplied with when drawn.
+    pub tint: Color,
+    /// Removed entities are no longer updated, drawn or collided with.
+    pub alive: bool,
+}
+
+impl Entity {
+    pub fn new(actor: Actor, sprite: SpriteSource, behavior: Behavior) -> Entity {
+        Entity {
+            actor,
+            sprite,
+            body: None,
+            behavior,
+            layer: behavior.render_layer(),
+            tint: Color::WHITE,
+            alive: true,
+        }
+    }
+
+    /// Returns the animation that is currently shown.
+    pub fn animation(&self) -> Option<&Animation> {
+        match &self.sprite {
+            SpriteSource::Animation(animator) => Some(animator.current()),
+            _ => None,
+        }
+    }
+
+    pub fn animation_mut(&mut self) -> Option<&mut Animation> {
+        self.animator_mut().map(|a| a.current_mut())
+    }
+
+    pub fn animator_mut(&mut self) -> Option<&mut Animator> {
+        match &mut self.sprite {
+            SpriteSource::Animation(animator) => Some(animator),
+            _ => None,
+        }
+    }
+
+    /// Switches to the given animation, using the animator's transition rules.
+    /// Does nothing if the entity is not animated.
+    pub fn play_animation(&mut self, def: Rc<AnimationDef>) {
+        if let Some(animator) = self.animator_mut() {
+            animator.play(def);
+        }
+    }
+
+    /// Returns the source rect of this entity's sprite in the texture atlas,
+    /// or `None` for entities drawn with meshes.
+    pub fn source_rect(&self, atlas: &TextureAtlas) -> Option<Rect> {
+        match &self.sprite {
+            SpriteSource::Tile { x, y } => {
+                let size = self.actor.sprite_size;
+                let tile = Rect::new(*x as f32 * size.x, *y as f32 * size.y, size.x, size.y);
+                Some(atlas.source_rect(GROUND_TILESET, tile))
+            }
+            SpriteSource::Animation(animator) => {
+                let animation = animator.current();
+                Some(sheet_frame_rect(
+                    &animation.def,
+                    animation.get_sheet_frame(),
+                    atlas,
+                ))
+            }
+            SpriteSource::Shape(_) => None,
+        }
+    }
+}
+
+/// Returns the source rect of a frame of an animation's sprite sheet in the texture atlas.
+/// Frames are numbered row by row from the upper left corner of the sheet.
+pub fn sheet_frame_rect(def: &AnimationDef, frame: usize, atlas: &TextureAtlas) -> Rect {
+    let size = def.frame_size;
+    let sheet_width = atlas.region(&def.sheet).w;
+    let columns = ((sheet_width / size.x) as usize).max(1);
+    let x = (frame % columns) as f32 * size.x;
+    let y = (frame / columns) as f32 * size.y;
+    atlas.source_rect(&def.sheet, Rect::new(x, y, size.x, size.y))
+}
+
+/// Holds the dynamic entities of the game.
+/// Entity ids stay valid until the store is cleared.
+#[derive(Debug, Default)]
+pub struct EntityStore {
+    entities: Vec<Entity>,
+}
+
+impl EntityStore {
+    pub fn spawn(&mut self, entity: Entity) -> EntityId {
+        self.entities.push(entity);
+        self.entities.len() - 1
+    }
+
+    pub fn get(&self, id: EntityId) -> &Entity {
+        &self.entities[id]
+    }
+
+    pub fn get_mut(&mut self, id: EntityId) -> &mut Entity {
+        &mut self.entities[id]
+    }
+
+    /// Iterates all entities that are alive.
+    pub fn iter(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.iter().filter(|e| e.alive)
+    }
+
+    /// Iterates all entities that are alive, with their ids.
+    pub fn iter_mut_with_ids(&mut self) -> impl Iterator<Item = (EntityId, &mut Entity)> {
+        self.entities
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, e)| e.alive)
+    }
+
+    /// Iterates all entities that are alive.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Entity> {
+        self.entities.iter_mut().filter(|e| e.alive)
+    }
+
+    pub fn clear(&mut self) {
+        self.entities.clear();
+    }
+
+    /// Removes all entities, and returns the one with the given id.
+    pub fn take_and_clear(&mut self, id: EntityId) -> Entity {
+        let entity = self.entities.swap_remove(id);
+        self.entities.clear();
+        entity
+    }
+}
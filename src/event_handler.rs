@@ -1,49 +1,65 @@
+use std::time::Instant;
+
 use ggez::event::EventHandler;
-use ggez::glam::Vec2;
-use ggez::graphics::Color;
 use ggez::input::keyboard::KeyInput;
-use ggez::{graphics, Context, GameResult};
+use ggez::{Context, GameResult};
 
 use crate::constants::DESIRED_FPS;
-use crate::game::SpriteGame;
+use crate::scene::SceneStack;
 
-impl EventHandler for SpriteGame {
+impl EventHandler for SceneStack {
     fn update(&mut self, ctx: &mut Context) -> GameResult {
-        while ctx.time.check_update_time(DESIRED_FPS) {
-            let seconds = 1.0 / (DESIRED_FPS as f32);
+        let game = &mut self.game;
+        if game.input.cycle_scale_mode {
+            game.input.cycle_scale_mode = false;
+            game.cycle_scale_mode(ctx);
+        }
+        if game.input.toggle_fullscreen {
+            game.input.toggle_fullscreen = false;
+            game.toggle_fullscreen(ctx)?;
+        }
 
-            if self.input.request_quit {
-                println!("Quitting game...");
-                ctx.request_quit()
-            }
+        let elapsed = ctx.time.delta().as_secs_f32();
+        self.game.reload_changed_level(ctx, elapsed);
+        self.game.gfx.reload_changed_assets(ctx, elapsed);
 
-            self.player.handle_input(&self.input, seconds, &self.level);
-            self.player.animation.update(seconds);
+        while ctx.time.check_update_time(DESIRED_FPS) {
+            let seconds = 1.0 / (DESIRED_FPS as f32);
+            let tick_start = Instant::now();
+            self.update_scene(ctx, seconds);
+            self.game.audio.update(seconds);
+            self.game.debug.tick_time = tick_start.elapsed();
         }
+        if !self.is_running() {
+            println!("Quitting game...");
+            ctx.request_quit();
+        }
+        self.game.debug.fps = ctx.time.fps();
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        let mut canvas = graphics::Canvas::from_frame(ctx, Color::WHITE);
-        let scale_factor = ctx.gfx.window().scale_factor() as f32;
-        let scale = Vec2 {
-            x: scale_factor,
-            y: scale_factor,
-        };
-        self.draw_frame(&mut canvas, scale);
-        canvas.finish(ctx)
+        // The game is drawn at its virtual resolution, and then scaled to the window
+        let mut canvas = self.game.viewport.canvas(ctx);
+        self.draw_scenes(&mut canvas);
+        canvas.finish(ctx)?;
+        self.game.viewport.present(ctx)
+    }
+
+    fn resize_event(&mut self, _ctx: &mut Context, width: f32, height: f32) -> GameResult {
+        self.game.viewport.resize(width, height);
+        Ok(())
     }
 
-    fn key_down_event(
-        &mut self,
-        _ctx: &mut Context,
-        input: KeyInput,
-        _repeated: bool,
-    ) -> GameResult {
-        self.input.handle_key_down(input)
+    fn key_down_event(&mut self, ctx: &mut Context, input: KeyInput, repeated: bool) -> GameResult {
+        self.game.input.handle_key_down(input)?;
+        if let (Some(key), false) = (input.keycode, repeated) {
+            self.key_pressed(ctx, key);
+        }
+        Ok(())
     }
 
     fn key_up_event(&mut self, _ctx: &mut Context, input: KeyInput) -> GameResult {
-        self.input.handle_key_up(input)
+        self.game.input.handle_key_up(input)
     }
 }
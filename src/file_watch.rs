@@ -0,0 +1,184 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// How often the watched files are checked, in seconds
+const POLL_INTERVAL: f32 = 0.5;
+/// How many times an unchanged file is reported again after it could not be loaded
+const MAX_RETRIES: u32 = 3;
+
+struct WatchedFile {
+    path: PathBuf,
+    /// Modification time when the file was last checked
+    modified: Option<SystemTime>,
+    /// Set when the file is to be reported again on the next check
+    retry: bool,
+    /// Retries since the file last changed
+    retries: u32,
+}
+
+impl WatchedFile {
+    fn new(path: PathBuf) -> WatchedFile {
+        let modified = modified_time(&path);
+        WatchedFile {
+            path,
+            modified,
+            retry: false,
+            retries: 0,
+        }
+    }
+}
+
+/// Notices changes to files by polling their modification times.
+pub struct FileWatcher {
+    files: Vec<WatchedFile>,
+    /// Time since the files were last checked, in seconds
+    elapsed: f32,
+}
+
+impl FileWatcher {
+    pub fn new(paths: impl IntoIterator<Item = PathBuf>) -> FileWatcher {
+        let files = paths.into_iter().map(WatchedFile::new).collect();
+        FileWatcher {
+            files,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Starts watching another file. Does nothing if the file is already watched.
+    pub fn watch(&mut self, path: PathBuf) {
+        if !self.files.iter().any(|f| f.path == path) {
+            self.files.push(WatchedFile::new(path));
+        }
+    }
+
+    /// Records the current modification time of a watched file, so that a change
+    /// the game made itself is not reported.
+    pub fn mark_seen(&mut self, path: &Path) {
+        if let Some(file) = self.files.iter_mut().find(|f| f.path == path) {
+            file.modified = modified_time(path);
+        }
+    }
+
+    /// Reports a file again on the next check while it exists. Used when a changed file
+    /// could not be loaded, for example because it was only partly written.
+    /// An unchanged file is retried at most `MAX_RETRIES` times; returns false once
+    /// the file is given up on until it changes again.
+    pub fn retry(&mut self, path: &Path) -> bool {
+        let Some(file) = self.files.iter_mut().find(|f| f.path == path) else {
+            return false;
+        };
+        if file.retries >= MAX_RETRIES {
+            return false;
+        }
+        file.retries += 1;
+        file.retry = true;
+        true
+    }
+
+    /// Checks the files once the poll interval has passed.
+    /// Returns the files that have been changed, created or removed since the last check.
+    pub fn poll(&mut self, seconds: f32) -> Vec<PathBuf> {
+        self.elapsed += seconds;
+        if self.elapsed < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.elapsed = 0.0;
+        self.changed()
+    }
+
+    /// Checks the files now, returning the ones that have changed since the last check.
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        for file in &mut self.files {
+            let current = modified_time(&file.path);
+            if current != file.modified {
+                file.modified = current;
+                file.retries = 0;
+                changed.push(file.path.clone());
+            } else if file.retry && current.is_some() {
+                changed.push(file.path.clone());
+            }
+            file.retry = false;
+        }
+        changed
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn reports_changed_files_once() {
+        let path = std::env::temp_dir().join(format!("file-watch-{}.txt", std::process::id()));
+        fs::write(&path, "level").unwrap();
+        let mut watcher = FileWatcher::new([path.clone()]);
+        assert!(watcher.changed().is_empty());
+
+        let later = SystemTime::now() + Duration::from_secs(10);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        assert!(
+            watcher.poll(0.1).is_empty(),
+            "checked before the poll interval"
+        );
+        assert_eq!(watcher.poll(POLL_INTERVAL), vec![path.clone()]);
+        assert!(watcher.changed().is_empty());
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(watcher.changed(), vec![path]);
+    }
+
+    #[test]
+    fn ignores_changes_marked_as_seen() {
+        let path = std::env::temp_dir().join(format!("file-watch-seen-{}.txt", std::process::id()));
+        fs::write(&path, "level").unwrap();
+        let mut watcher = FileWatcher::new([path.clone()]);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        watcher.mark_seen(&path);
+        assert!(watcher.changed().is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reports_retried_files_again() {
+        let path =
+            std::env::temp_dir().join(format!("file-watch-retry-{}.txt", std::process::id()));
+        fs::write(&path, "image").unwrap();
+        let mut watcher = FileWatcher::new([path.clone()]);
+        for _ in 0..MAX_RETRIES {
+            assert!(watcher.retry(&path));
+            assert_eq!(watcher.changed(), vec![path.clone()]);
+            assert!(watcher.changed().is_empty());
+        }
+        assert!(!watcher.retry(&path), "gave up on the unchanged file");
+        assert!(watcher.changed().is_empty());
+
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(watcher.changed(), vec![path.clone()]);
+        assert!(watcher.retry(&path), "retried again after a change");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,52 +1,400 @@
-use ggez::glam::Vec2;
-use ggez::graphics::{Canvas, DrawParam};
+use std::rc::Rc;
+
+use ggez::graphics::Canvas;
 use ggez::{Context, GameResult};
 
-use crate::actor::Actor;
-use crate::constants::DRAW_BBOX;
+use crate::animation_handler::AnimationLibrary;
+use crate::audio::{
+    AudioManager, GgezAudio, NullAudio, Volumes, FOOTSTEP_SOUND, JUMP_SOUND, LAND_SOUND,
+};
+use crate::collectible::Score;
+use crate::constants::{
+    GROUND_TILE_HEIGHT, GROUND_TILE_WIDTH, LEVEL_FILE, LEVEL_HEIGHT, LEVEL_WIDTH,
+};
+use crate::debug_overlay::{draw_debug_overlay, DebugStats};
+use crate::enemy::{hurt, patrol};
+use crate::entity::{Behavior, Entity, EntityId, EntityStore, RenderLayer};
+use crate::file_watch::FileWatcher;
 use crate::game_gfx::GraphicsHandler;
+use crate::hud::draw_hud;
 use crate::input_handler::InputState;
 use crate::level_handler::LevelHandler;
-use crate::player::Player;
+use crate::physics::PhysicsBody;
+use crate::player::{Player, PlayerState, START_POSITION};
+use crate::primitives::{Point2, RectExt};
+use crate::save_game::SaveGame;
+use crate::settings::Settings;
+use crate::speedrun::{GhostFrame, Run, Speedrun};
+use crate::viewport::Viewport;
+
+/// Animation event that plays a footstep sound
+const FOOTSTEP_EVENT: &str = "footstep";
 
 pub struct SpriteGame {
     pub player: Player,
+    pub entities: EntityStore,
     pub input: InputState,
     pub level: LevelHandler,
+    pub animations: AnimationLibrary,
+    pub score: Score,
+    /// Time spent playing the current level, in seconds
+    pub level_time: f32,
     pub gfx: GraphicsHandler,
+    pub debug: DebugStats,
+    pub viewport: Viewport,
+    pub settings: Settings,
+    pub save: SaveGame,
+    /// Notices changes to the level files, so that the level can be reloaded while playing
+    level_watch: FileWatcher,
+    /// Split times of the current run, and the best run of the level as a ghost
+    pub speedrun: Speedrun,
+    pub audio: AudioManager,
+    /// Animation events triggered during the current update, with the entity that triggered them
+    pub animation_events: Vec<(EntityId, Rc<str>)>,
 }
 
 impl SpriteGame {
     pub fn new(ctx: &mut Context) -> GameResult<SpriteGame> {
-        let player = Player::create();
-        let input = InputState::default();
-        let level = LevelHandler::new("level.txt", 40, 23)?;
-        let gfx = GraphicsHandler::new(ctx)?;
+        let mut input = InputState::default();
+        let animations = AnimationLibrary::load(ctx, "/animations.txt")?;
+        let mut level = LevelHandler::new(LEVEL_FILE, LEVEL_WIDTH, LEVEL_HEIGHT)?;
+        let mut entities = EntityStore::default();
+        let player = Self::spawn_entities(&mut level, &mut entities, &animations);
+        let score = Score::new(level.collectible_spawns.len());
+        let gfx = GraphicsHandler::new(ctx, &animations, &level.background)?;
+        let settings = Settings::load(&Settings::path(ctx));
+        if settings.fullscreen {
+            ctx.gfx.set_fullscreen(settings.fullscreen_type())?;
+        }
+        let audio = Self::create_audio(ctx, &settings);
+        input.bindings = settings.bindings.clone();
+        let save = SaveGame::load(&SaveGame::path(ctx));
+        let level_watch = FileWatcher::new(level.files());
+        let speedrun = Speedrun::new(Run::load(&Run::path(ctx, &level.name)));
 
         Ok(SpriteGame {
             player,
+            entities,
             input,
             level,
+            animations,
+            score,
+            level_time: 0.0,
             gfx,
+            debug: DebugStats::default(),
+            viewport: Viewport::new(ctx, settings.scale_mode),
+            settings,
+            save,
+            level_watch,
+            speedrun,
+            audio,
+            animation_events: Vec::new(),
         })
     }
 
-    pub fn traverse_actors<F>(&self, mut callback: F)
+    /// Plays audio through ggez, or silently if the sound data cannot be loaded.
+    fn create_audio(ctx: &Context, settings: &Settings) -> AudioManager {
+        let mut audio = match GgezAudio::load(ctx, "/sounds.txt") {
+            Ok(backend) => AudioManager::new(Box::new(backend)),
+            Err(e) => {
+                println!("Could not load sounds, playing without audio: {}", e);
+                AudioManager::new(Box::<NullAudio>::default())
+            }
+        };
+        audio.set_volumes(settings.volumes);
+        audio
+    }
+
+    /// Switches between borderless fullscreen and windowed mode, and remembers the choice.
+    pub fn toggle_fullscreen(&mut self, ctx: &mut Context) -> GameResult {
+        self.settings.fullscreen = !self.settings.fullscreen;
+        ctx.gfx.set_fullscreen(self.settings.fullscreen_type())?;
+        let (width, height) = ctx.gfx.drawable_size();
+        self.viewport.resize(width, height);
+        self.save_settings(ctx);
+        Ok(())
+    }
+
+    /// Switches to the next scale mode, and remembers the choice.
+    pub fn cycle_scale_mode(&mut self, ctx: &Context) {
+        self.settings.scale_mode = self.settings.scale_mode.next();
+        self.viewport.mode = self.settings.scale_mode;
+        println!("Scale mode: {}", self.settings.scale_mode.name());
+        self.save_settings(ctx);
+    }
+
+    /// Changes audio volumes, and remembers them.
+    pub fn set_volumes(&mut self, ctx: &Context, volumes: Volumes) {
+        self.settings.volumes = volumes;
+        self.audio.set_volumes(volumes);
+        self.save_settings(ctx);
+    }
+
+    /// Records the completion of the current level in the save game, and saves it.
+    /// The run is kept as the level's ghost if it was the fastest one.
+    /// Returns true if the level was completed faster than before.
+    pub fn complete_level(&mut self, ctx: &Context) -> bool {
+        let new_best =
+            self.save
+                .complete_level(&self.level.name, self.level_time, self.score.collected);
+        let path = SaveGame::path(ctx);
+        if let Err(e) = self.save.save(&path) {
+            println!("Could not save game to {:?}: {}", path, e);
+        }
+        if let Some(run) = self.speedrun.finish() {
+            let path = Run::path(ctx, &self.level.name);
+            if let Err(e) = run.save(&path) {
+                println!("Could not save ghost to {:?}: {}", path, e);
+            }
+        }
+        new_best
+    }
+
+    /// Failing to save settings is not fatal; the game continues with the current settings.
+    fn save_settings(&self, ctx: &Context) {
+        let path = Settings::path(ctx);
+        if let Err(e) = self.settings.save(&path) {
+            println!("Could not save settings to {:?}: {}", path, e);
+        }
+    }
+
+    /// Puts the player back to the start and resets the level's entities.
+    pub fn restart_level(&mut self) {
+        self.entities.clear();
+        self.player = Self::spawn_entities(&mut self.level, &mut self.entities, &self.animations);
+        self.score = Score::new(self.level.collectible_spawns.len());
+        self.level_time = 0.0;
+        self.speedrun.restart();
+    }
+
+    /// Writes the edited level to its level file. The write is not reported
+    /// as a change by the level file watcher.
+    pub fn save_level(&mut self) -> GameResult {
+        self.level.save()?;
+        self.level_watch.mark_seen(self.level.path());
+        Ok(())
+    }
+
+    /// Reloads the level if its files have been changed on disk, for example in a text editor.
+    /// A level with unsaved changes from the level editor is not replaced.
+    pub fn reload_changed_level(&mut self, ctx: &Context, seconds: f32) {
+        if self.level_watch.poll(seconds).is_empty() {
+            return;
+        }
+        if self.level.is_modified() {
+            println!("Level files changed on disk; keeping the unsaved changes in the editor");
+            return;
+        }
+        match self.reload_level(ctx) {
+            Ok(()) => println!("Reloaded level {}", self.level.name),
+            Err(e) => println!("Could not reload level, keeping the old one: {}", e),
+        }
+    }
+
+    /// Replaces the level with the one on disk without restarting the run. Collectibles and
+    /// enemies are spawned from the new level, leaving out the items already collected, and
+    /// the score counts the collected items that are still in the level. The player stays
+    /// where they were if that place is still open in the new level, and is moved back
+    /// to the start otherwise.
+    fn reload_level(&mut self, ctx: &Context) -> GameResult {
+        let level = self.level.reload()?;
+        self.gfx.assets.load_backgrounds(ctx, &level.background)?;
+        self.level = level;
+
+        let mut player = self.entities.take_and_clear(self.player.entity);
+        let speedrun = &self.speedrun;
+        self.level
+            .spawn_entities(&mut self.entities, &self.animations, |tile| {
+                speedrun.is_collected(tile)
+            });
+        self.score = Score::new(self.level.collectible_spawns.len());
+        for &(kind, x, y) in &self.level.collectible_spawns {
+            if speedrun.is_collected((x, y)) {
+                self.score.add(kind);
+            }
+        }
+
+        let bbox = player.actor.bbox;
+        if !self.level.bounds().overlaps(&bbox) || self.level.collides_with(&bbox) {
+            player.actor.pos = START_POSITION;
+            player.actor.update_bbox();
+            if let Some(body) = &mut player.body {
+                *body = PhysicsBody::new();
+            }
+        }
+        self.player.entity = self.entities.spawn(player);
+        Ok(())
+    }
+
+    /// Moves the player to stand on the given tile, for test-playing from the level editor.
+    pub fn place_player(&mut self, x: usize, y: usize) {
+        let actor = &mut self.entities.get_mut(self.player.entity).actor;
+        actor.pos = Point2::new(x as f32 * GROUND_TILE_WIDTH, y as f32 * GROUND_TILE_HEIGHT);
+        actor.update_bbox();
+    }
+
+    fn spawn_entities(
+        level: &mut LevelHandler,
+        entities: &mut EntityStore,
+        animations: &AnimationLibrary,
+    ) -> Player {
+        level.reset(entities, animations);
+        let id = entities.spawn(Player::create_entity(animations));
+        Player::create(id, animations)
+    }
+
+    pub fn player_entity(&self) -> &Entity {
+        self.entities.get(self.player.entity)
+    }
+
+    /// Runs entity behaviors, and then moves all entities that have a physics body.
+    pub fn update_entities(&mut self, seconds: f32) {
+        self.level_time += seconds;
+        let was_jumping = matches!(self.player.state, PlayerState::Jumping);
+        let was_grounded = self.player_body_grounded();
+        let player = self.entities.get_mut(self.player.entity);
+        self.player.handle_input(player, &self.input, seconds);
+        self.input.attack_pressed = false;
+        let player_x = player.actor.pos.x;
+
+        self.animation_events.clear();
+        for (id, entity) in self.entities.iter_mut_with_ids() {
+            patrol(entity, seconds, &self.level, &self.animations);
+            if let Some(body) = &mut entity.body {
+                body.update(&mut entity.actor, seconds, &self.level);
+            }
+            if id == self.player.entity {
+                let moved_x = entity.actor.pos.x - player_x;
+                self.player.update_animation_speed(entity, moved_x, seconds);
+            }
+            if let Some(animator) = entity.animator_mut() {
+                for event in animator.update(seconds) {
+                    self.animation_events.push((id, event));
+                }
+            }
+        }
+        self.handle_animation_events();
+
+        if !was_jumping && matches!(self.player.state, PlayerState::Jumping) {
+            self.audio.play_sound(JUMP_SOUND);
+        }
+        if !was_grounded && self.player_body_grounded() {
+            self.audio.play_sound(LAND_SOUND);
+        }
+        if let Some(frame) = GhostFrame::capture(self.player_entity()) {
+            self.speedrun.record(self.level_time, frame);
+        }
+    }
+
+    fn player_body_grounded(&self) -> bool {
+        self.player_entity()
+            .body
+            .as_ref()
+            .is_some_and(|b| b.grounded)
+    }
+
+    fn handle_animation_events(&mut self) {
+        for (id, event) in &self.animation_events {
+            if *id == self.player.entity {
+                self.player.handle_animation_event(event);
+                if &**event == FOOTSTEP_EVENT {
+                    self.audio.play_sound(FOOTSTEP_SOUND);
+                }
+            }
+        }
+    }
+
+    /// Damages the player on contact with hostile entities.
+    pub fn check_enemy_contact(&mut self) {
+        let bbox = self.player_entity().actor.bbox;
+        if self
+            .entities
+            .iter()
+            .any(|e| e.behavior.is_hostile() && e.actor.bbox.collides_with(&bbox))
+        {
+            let player = self.entities.get_mut(self.player.entity);
+            self.player.take_hit(player);
+        }
+    }
+
+    /// Defeats enemies and breaks tiles that are hit by the player's attack.
+    pub fn check_attack_hits(&mut self) {
+        let Some(hitbox) = self.player.attack_hitbox(self.player_entity()) else {
+            return;
+        };
+        for entity in self.entities.iter_mut() {
+            if entity.behavior.is_attackable() && entity.actor.bbox.collides_with(&hitbox) {
+                hurt(entity, &self.animations);
+            }
+        }
+        self.level.break_tiles(&hitbox);
+    }
+
+    pub fn collect_items(&mut self) {
+        let bbox = self.player_entity().actor.bbox;
+        for entity in self.entities.iter_mut() {
+            if let Behavior::Collectible(kind) = entity.behavior {
+                if entity.actor.bbox.collides_with(&bbox) {
+                    entity.alive = false;
+                    self.score.add(kind);
+                    // Collectibles do not move, so their tile is the one they spawned on
+                    let pos = entity.actor.pos;
+                    let tile = (
+                        (pos.x / GROUND_TILE_WIDTH) as usize,
+                        (pos.y / GROUND_TILE_HEIGHT) as usize,
+                    );
+                    self.speedrun.split(tile, self.level_time);
+                }
+            }
+        }
+    }
+
+    pub fn traverse_entities<F>(&self, mut callback: F)
     where
-        F: FnMut(&Actor),
+        F: FnMut(&Entity),
     {
-        for a in &self.level.actors {
-            callback(a);
+        for t in self.level.tiles.iter().filter(|t| t.alive) {
+            callback(t);
+        }
+        for e in self.entities.iter() {
+            callback(e);
+        }
+    }
+
+    fn draw_layer(&mut self, layer: RenderLayer, canvas: &mut Canvas) {
+        match layer {
+            RenderLayer::Background => {
+                self.gfx
+                    .draw_background(&self.level.background, self.parallax_scroll(), canvas)
+            }
+            RenderLayer::Hud => draw_hud(self, canvas),
+            _ => {
+                self.gfx.draw_tiles(&self.level, layer, canvas);
+                if let (RenderLayer::Player, Some(ghost)) = (layer, self.speedrun.ghost()) {
+                    self.gfx.draw_ghost(ghost, &self.animations, canvas);
+                }
+                for e in self.entities.iter().filter(|e| e.layer == layer) {
+                    self.gfx.draw_entity(e, canvas);
+                }
+            }
         }
-        callback(&self.player.actor);
     }
 
-    pub fn draw_frame(&mut self, canvas: &mut Canvas, scale: Vec2) {
-        canvas.draw(&self.gfx.assets.background, DrawParam::new().scale(scale));
-        self.traverse_actors(|a| self.gfx.draw_actor(a, canvas, self, scale));
+    /// Horizontal scroll position for background layers: the player's distance
+    /// from the center of the level, so the background shifts as the player moves.
+    fn parallax_scroll(&self) -> f32 {
+        let bbox = self.player_entity().actor.bbox;
+        let level = self.level.bounds();
+        (bbox.x + bbox.w / 2.0) - (level.x + level.w / 2.0)
+    }
+
+    pub fn draw_frame(&mut self, canvas: &mut Canvas) {
+        for layer in RenderLayer::ALL {
+            self.draw_layer(layer, canvas);
+        }
 
-        if DRAW_BBOX {
-            self.traverse_actors(|a| self.gfx.draw_bbox(a, canvas, scale));
+        if self.input.debug_overlay {
+            draw_debug_overlay(self, canvas);
         }
     }
 }
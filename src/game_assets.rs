@@ -1,34 +1,136 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
 use ggez::graphics::Image;
 use ggez::{Context, GameResult};
 
-use crate::actor::{Actor, ActorType};
-use crate::game::SpriteGame;
+use crate::animation_handler::AnimationLibrary;
+use crate::atlas::TextureAtlas;
+use crate::constants::RESOURCE_DIR;
+use crate::file_watch::FileWatcher;
+use crate::parallax::ParallaxLayer;
+
+/// Path of the tileset that level tiles are drawn from.
+pub const GROUND_TILESET: &str = "/background-tileset.png";
 
 pub struct GameAssets {
-    pub background: Image,
-    pub player_idle_tiles: Image,
-    pub player_walk_tiles: Image,
-    pub player_run_tiles: Image,
-    pub player_jump_tiles: Image,
-    pub ground_tiles: Image,
+    /// Background layer images, by path
+    backgrounds: HashMap<String, Image>,
+    /// Ground tileset and animation sprite sheets, packed by path
+    pub atlas: TextureAtlas,
+    /// Paths of the images in the atlas
+    sheets: Vec<String>,
+    /// Watches the loaded image files for changes in development mode
+    watcher: Option<FileWatcher>,
 }
 
 impl GameAssets {
-    pub fn new(ctx: &mut Context) -> GameResult<GameAssets> {
-        Ok(GameAssets {
-            background: Image::from_path(ctx, "/background.png")?,
-            player_idle_tiles: Image::from_path(ctx, "/idle-tileset.png")?,
-            player_run_tiles: Image::from_path(ctx, "/run-tileset.png")?,
-            player_walk_tiles: Image::from_path(ctx, "/walk-tileset.png")?,
-            player_jump_tiles: Image::from_path(ctx, "/jump-tileset.png")?,
-            ground_tiles: Image::from_path(ctx, "/background-tileset.png")?,
-        })
+    pub fn new(
+        ctx: &mut Context,
+        animations: &AnimationLibrary,
+        background: &[ParallaxLayer],
+    ) -> GameResult<GameAssets> {
+        let sheets: Vec<String> = std::iter::once(GROUND_TILESET)
+            .chain(animations.sheets())
+            .map(String::from)
+            .collect();
+        let mut assets = GameAssets {
+            backgrounds: HashMap::new(),
+            atlas: TextureAtlas::build(ctx, sheets.iter().map(String::as_str))?,
+            sheets,
+            watcher: None,
+        };
+        assets.load_backgrounds(ctx, background)?;
+        Ok(assets)
+    }
+
+    /// Loads the images of background layers that have not been loaded yet.
+    pub fn load_backgrounds(&mut self, ctx: &Context, background: &[ParallaxLayer]) -> GameResult {
+        for layer in background {
+            if !self.backgrounds.contains_key(&layer.image) {
+                let image = Image::from_path(ctx, &layer.image)?;
+                self.backgrounds.insert(layer.image.clone(), image);
+                if let Some(watcher) = &mut self.watcher {
+                    watcher.watch(resource_file(&layer.image));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts watching the loaded images, so that changed files can be reloaded
+    /// with `reload_changed` without restarting the game.
+    pub fn enable_hot_reload(&mut self) {
+        let paths = self.sheets.iter().chain(self.backgrounds.keys());
+        self.watcher = Some(FileWatcher::new(paths.map(|p| resource_file(p))));
     }
 
-    pub fn actor_image(&self, actor: &Actor, game: &SpriteGame) -> &Image {
-        match actor.tag {
-            ActorType::Player => (game.player.animation.tileset_image)(self),
-            ActorType::GroundBlock { x: _, y: _ } => &self.ground_tiles,
+    /// Reloads the images whose files have changed. Sprite sheets are packed again
+    /// into a new atlas, as their size may have changed. Images that cannot be loaded,
+    /// such as a partly written file, are reported and tried again on the next few checks,
+    /// and after that only once they change again; the old images are kept until then.
+    /// Returns true if the atlas image was replaced.
+    pub fn reload_changed(&mut self, ctx: &mut Context, seconds: f32) -> bool {
+        let Some(watcher) = &mut self.watcher else {
+            return false;
+        };
+        let mut changed_sheets = Vec::new();
+        for file in watcher.poll(seconds) {
+            println!("Reloading {:?}", file);
+            if self.sheets.iter().any(|s| resource_file(s) == file) {
+                changed_sheets.push(file.clone());
+            }
+            for (path, image) in &mut self.backgrounds {
+                if resource_file(path) != file {
+                    continue;
+                }
+                match Image::from_path(ctx, path.as_str()) {
+                    Ok(new_image) => *image = new_image,
+                    Err(e) => {
+                        println!("Could not reload {:?}, keeping the old image: {}", file, e);
+                        retry(watcher, &file);
+                    }
+                }
+            }
+        }
+        if changed_sheets.is_empty() {
+            return false;
         }
+        match TextureAtlas::build(ctx, self.sheets.iter().map(String::as_str)) {
+            Ok(atlas) => {
+                self.atlas = atlas;
+                true
+            }
+            Err(e) => {
+                println!(
+                    "Could not rebuild the sprite atlas, keeping the old one: {}",
+                    e
+                );
+                for file in &changed_sheets {
+                    retry(watcher, file);
+                }
+                false
+            }
+        }
+    }
+
+    /// Returns a background layer image that was loaded for the level.
+    pub fn background(&self, path: &str) -> &Image {
+        self.backgrounds
+            .get(path)
+            .unwrap_or_else(|| panic!("Background {} is not loaded", path))
+    }
+}
+
+/// Returns the file of a resource path, such as `/background.png`.
+fn resource_file(path: &str) -> PathBuf {
+    Path::new(RESOURCE_DIR).join(path.trim_start_matches('/'))
+}
+
+/// Asks the watcher to report a file that could not be loaded again,
+/// and tells when it has been given up on.
+fn retry(watcher: &mut FileWatcher, file: &Path) {
+    if !watcher.retry(file) {
+        println!("Giving up on {:?} until it is changed again", file);
     }
 }
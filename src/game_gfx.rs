@@ -1,85 +1,352 @@
 use ggez::glam::Vec2;
-use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, Mesh, MeshBuilder, Rect};
+use ggez::graphics::{Canvas, Color, DrawMode, DrawParam, InstanceArray, Mesh, MeshBuilder, Rect};
 use ggez::{Context, GameResult};
 
-use crate::actor::{Actor, ActorType};
-use crate::constants::{
-    GROUND_TILE_HEIGHT, GROUND_TILE_WIDTH, PLAYER_BBOX_HEIGHT, PLAYER_BBOX_WIDTH, SCREEN_HEIGHT,
-};
-use crate::game::SpriteGame;
-use crate::game_assets::GameAssets;
+use crate::actor::Actor;
+use crate::animation_handler::AnimationLibrary;
+use crate::atlas::TextureAtlas;
+use crate::constants::{GROUND_TILE_HEIGHT, GROUND_TILE_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::entity::{sheet_frame_rect, Entity, RenderLayer, Shape, SpriteSource};
+use crate::game_assets::{GameAssets, GROUND_TILESET};
+use crate::level_handler::{LevelHandler, TileType};
+use crate::parallax::{Anchor, ParallaxLayer, Repeat};
 use crate::primitives::{Direction, Point2};
+use crate::speedrun::GhostFrame;
+
+/// Tint of the best run's ghost knight
+const GHOST_COLOR: Color = Color::new(0.6, 0.8, 1.0, 0.4);
 
 pub struct GraphicsHandler {
     pub assets: GameAssets,
-    player_bbox: Mesh,
-    ground_bbox: Mesh,
+    coin: Mesh,
+    gem: Mesh,
+    line: Mesh,
+    tile_grid: Mesh,
+    /// Level tiles in unscaled screen coordinates, one batch per render layer,
+    /// so that each layer is drawn with a single draw call
+    tiles: Vec<InstanceArray>,
+    /// Level revision the tile batch was built from
+    tiles_revision: Option<u64>,
 }
 
 impl GraphicsHandler {
-    pub fn new(ctx: &mut Context) -> GameResult<GraphicsHandler> {
-        let assets = GameAssets::new(ctx).expect("Could not initialize Game Assets");
-        let player_bbox =
-            Self::create_bbox(ctx, PLAYER_BBOX_WIDTH, PLAYER_BBOX_HEIGHT, Color::GREEN)?;
-        let ground_bbox =
-            Self::create_bbox(ctx, GROUND_TILE_WIDTH, GROUND_TILE_HEIGHT, Color::BLUE)?;
+    pub fn new(
+        ctx: &mut Context,
+        animations: &AnimationLibrary,
+        background: &[ParallaxLayer],
+    ) -> GameResult<GraphicsHandler> {
+        let assets =
+            GameAssets::new(ctx, animations, background).expect("Could not initialize Game Assets");
+        let coin = Self::create_coin(ctx)?;
+        let gem = Self::create_gem(ctx)?;
+        let line = Self::create_line(ctx)?;
+        let tile_grid = Self::create_tile_grid(ctx)?;
+        let tiles = RenderLayer::ALL
+            .iter()
+            .map(|_| InstanceArray::new(ctx, assets.atlas.image.clone()))
+            .collect();
         Ok(GraphicsHandler {
             assets,
-            player_bbox,
-            ground_bbox,
+            coin,
+            gem,
+            line,
+            tile_grid,
+            tiles,
+            tiles_revision: None,
         })
     }
 
-    fn create_bbox(ctx: &mut Context, width: f32, height: f32, color: Color) -> GameResult<Mesh> {
+    /// Reloads changed images in development mode. The tile batches are created again
+    /// if the texture atlas was replaced.
+    pub fn reload_changed_assets(&mut self, ctx: &mut Context, seconds: f32) {
+        if self.assets.reload_changed(ctx, seconds) {
+            self.tiles = RenderLayer::ALL
+                .iter()
+                .map(|_| InstanceArray::new(ctx, self.assets.atlas.image.clone()))
+                .collect();
+            self.tiles_revision = None;
+        }
+    }
+
+    /// Coin shape, in unit size. Scaled to actor draw size when drawn.
+    fn create_coin(ctx: &mut Context) -> GameResult<Mesh> {
+        let mut mb = MeshBuilder::new();
+        let center = Point2::new(0.5, 0.5);
+        let coin_data = mb
+            .circle(
+                DrawMode::fill(),
+                center,
+                0.5,
+                0.01,
+                Color::from_rgb(240, 190, 40),
+            )?
+            .circle(
+                DrawMode::fill(),
+                center,
+                0.3,
+                0.01,
+                Color::from_rgb(255, 220, 90),
+            )?
+            .build();
+        Ok(Mesh::from_data(ctx, coin_data))
+    }
+
+    /// Gem shape, in unit size. Scaled to actor draw size when drawn.
+    fn create_gem(ctx: &mut Context) -> GameResult<Mesh> {
+        let mut mb = MeshBuilder::new();
+        let points = [
+            Point2::new(0.5, 0.0),
+            Point2::new(1.0, 0.4),
+            Point2::new(0.5, 1.0),
+            Point2::new(0.0, 0.4),
+        ];
+        let gem_data = mb
+            .polygon(DrawMode::fill(), &points, Color::from_rgb(80, 200, 240))?
+            .build();
+        Ok(Mesh::from_data(ctx, gem_data))
+    }
+
+    /// Unit length line along the x-axis, centered on the y-axis.
+    /// Use draw param scale to set length and width, and rotation to set direction.
+    fn create_line(ctx: &mut Context) -> GameResult<Mesh> {
         let mut mb = MeshBuilder::new();
-        let ratio = (width + height) / 2.0;
-        let bbox_data = mb
+        let line_data = mb
             .rectangle(
-                DrawMode::stroke(1.0 / ratio),
+                DrawMode::fill(),
                 Rect {
                     x: 0.0,
-                    y: 0.0,
+                    y: -0.5,
                     w: 1.0,
                     h: 1.0,
                 },
-                color,
+                Color::WHITE,
             )?
             .build();
-        Ok(Mesh::from_data(ctx, bbox_data))
+        Ok(Mesh::from_data(ctx, line_data))
+    }
+
+    /// Grid lines at tile boundaries, in unscaled screen coordinates.
+    /// Tiles are aligned to the bottom of the screen.
+    fn create_tile_grid(ctx: &mut Context) -> GameResult<Mesh> {
+        let mut mb = MeshBuilder::new();
+        let color = Color::new(1.0, 1.0, 1.0, 0.3);
+        let mut x = 0.0;
+        while x <= SCREEN_WIDTH {
+            mb.line(
+                &[Vec2::new(x, 0.0), Vec2::new(x, SCREEN_HEIGHT)],
+                1.0,
+                color,
+            )?;
+            x += GROUND_TILE_WIDTH;
+        }
+        let mut y = SCREEN_HEIGHT;
+        while y >= 0.0 {
+            mb.line(&[Vec2::new(0.0, y), Vec2::new(SCREEN_WIDTH, y)], 1.0, color)?;
+            y -= GROUND_TILE_HEIGHT;
+        }
+        Ok(Mesh::from_data(ctx, mb.build()))
     }
 
-    pub fn get_screen_coords(src: &Rect, scale: &Vec2) -> Rect {
+    pub fn get_screen_coords(src: &Rect) -> Rect {
         let y = SCREEN_HEIGHT - src.y - src.h;
         Rect {
-            x: (src.x * scale.x).round(),
-            y: (y * scale.y).round(),
-            w: (src.w * scale.x).round(),
-            h: (src.h * scale.y).round(),
+            x: src.x.round(),
+            y: y.round(),
+            w: src.w.round(),
+            h: src.h.round(),
         }
     }
 
-    pub fn draw_actor(&self, actor: &Actor, canvas: &mut Canvas, game: &SpriteGame, scale: Vec2) {
-        let img = self.assets.actor_image(actor, game);
-        let src = actor.tile_offset(img, game);
-        let dest = Self::get_screen_coords(&actor.draw_rect(), &scale);
-        let params = DrawParam::new().src(src).dest(dest.point());
+    /// Converts a point in game space to screen coordinates
+    pub fn get_screen_point(src: Point2) -> Point2 {
+        Point2::new(src.x, SCREEN_HEIGHT - src.y)
+    }
+
+    /// Draws background layers back to front, offset by their parallax factor
+    /// from the horizontal scroll position.
+    pub fn draw_background(&self, layers: &[ParallaxLayer], scroll: f32, canvas: &mut Canvas) {
+        for layer in layers {
+            let image = self.assets.background(&layer.image);
+            let size = Vec2::new(image.width() as f32, image.height() as f32);
+            let image_scale = match layer.anchor {
+                Anchor::Fill => SCREEN_HEIGHT / size.y,
+                Anchor::Top | Anchor::Bottom => 1.0,
+            };
+            let size = size * image_scale;
+            let y = match layer.anchor {
+                Anchor::Bottom => SCREEN_HEIGHT - size.y,
+                Anchor::Fill | Anchor::Top => 0.0,
+            };
+            let offset = layer.offset(scroll);
+            let mut x = match layer.repeat {
+                // Start from the copy that covers the left edge of the screen
+                Repeat::X => offset.rem_euclid(size.x) - size.x,
+                Repeat::None => offset,
+            };
+            loop {
+                canvas.draw(
+                    image,
+                    DrawParam::new()
+                        .dest(Point2::new(x, y))
+                        .scale(Vec2::splat(image_scale)),
+                );
+                x += size.x;
+                if layer.repeat == Repeat::None || x >= SCREEN_WIDTH {
+                    break;
+                }
+            }
+        }
+    }
 
-        let facing = match actor.facing {
+    pub fn draw_entity(&self, entity: &Entity, canvas: &mut Canvas) {
+        match Self::sprite_param(entity, &self.assets.atlas) {
+            Some(params) => canvas.draw(&self.assets.atlas.image, params),
+            None => self.draw_shape(entity, canvas),
+        }
+    }
+
+    /// Draws a translucent knight at a recorded position of the best run.
+    /// Frames of animations that are no longer defined are skipped.
+    pub fn draw_ghost(
+        &self,
+        ghost: &GhostFrame,
+        animations: &AnimationLibrary,
+        canvas: &mut Canvas,
+    ) {
+        let Some(def) = animations.find(&ghost.animation) else {
+            return;
+        };
+        let atlas = &self.assets.atlas;
+        let src = sheet_frame_rect(&def, ghost.frame, atlas);
+        let actor = Actor::create_knight(ghost.pos, ghost.facing);
+        canvas.draw(
+            &atlas.image,
+            Self::actor_param(&actor, src).color(GHOST_COLOR),
+        );
+    }
+
+    /// Draws the sprite of a level tile to a rectangle given in screen coordinates.
+    pub fn draw_tile_icon(&self, tile: &TileType, dest: Rect, canvas: &mut Canvas) {
+        let size = Vec2::new(GROUND_TILE_WIDTH, GROUND_TILE_HEIGHT);
+        let frame = Rect::new(
+            tile.x as f32 * size.x,
+            tile.y as f32 * size.y,
+            size.x,
+            size.y,
+        );
+        let atlas = &self.assets.atlas;
+        canvas.draw(
+            &atlas.image,
+            DrawParam::new()
+                .src(atlas.source_rect(GROUND_TILESET, frame))
+                .dest(dest.point())
+                .scale(Vec2::new(dest.w, dest.h) / size),
+        );
+    }
+
+    /// Draws the level tiles on the given layer. The tile batches are only rebuilt
+    /// when the level has changed since they were last drawn.
+    pub fn draw_tiles(&mut self, level: &LevelHandler, layer: RenderLayer, canvas: &mut Canvas) {
+        if self.tiles_revision != Some(level.revision()) {
+            let atlas = &self.assets.atlas;
+            for (batch, batch_layer) in self.tiles.iter_mut().zip(RenderLayer::ALL) {
+                batch.set(
+                    level
+                        .tiles
+                        .iter()
+                        .filter(|t| t.alive && t.layer == batch_layer)
+                        .filter_map(|t| Self::sprite_param(t, atlas)),
+                );
+            }
+            self.tiles_revision = Some(level.revision());
+        }
+        canvas.draw(&self.tiles[layer as usize], DrawParam::new());
+    }
+
+    /// Returns the draw params for an entity's sprite in the texture atlas,
+    /// or `None` for entities drawn with meshes.
+    fn sprite_param(entity: &Entity, atlas: &TextureAtlas) -> Option<DrawParam> {
+        let src = entity.source_rect(atlas)?;
+        Some(Self::actor_param(&entity.actor, src).color(entity.tint))
+    }
+
+    /// Returns the draw params for a sprite drawn at an actor's position, facing its direction.
+    fn actor_param(actor: &Actor, src: Rect) -> DrawParam {
+        let dest = Self::get_screen_coords(&actor.draw_rect());
+        let params = DrawParam::new().src(src).dest(dest.point());
+        match actor.facing {
             Direction::Left => params
-                .scale(Vec2::new(-scale.x, scale.y))
+                .scale(Vec2::new(-1.0, 1.0))
                 .offset(Point2::new(1.0, 0.0)),
-            _ => params.scale(scale),
+            _ => params,
+        }
+    }
+
+    fn draw_shape(&self, entity: &Entity, canvas: &mut Canvas) {
+        let mesh = match entity.sprite {
+            SpriteSource::Shape(Shape::Coin) => &self.coin,
+            SpriteSource::Shape(Shape::Gem) => &self.gem,
+            _ => return,
         };
+        let rect = Self::get_screen_coords(&entity.actor.draw_rect());
+        canvas.draw(
+            mesh,
+            DrawParam::new()
+                .dest(rect.point())
+                .scale(rect.size())
+                .color(entity.tint),
+        );
+    }
 
-        canvas.draw(img, facing)
+    pub fn draw_bbox(&self, entity: &Entity, canvas: &mut Canvas) {
+        self.draw_outline(&entity.actor.bbox, entity.behavior.debug_color(), canvas);
     }
 
-    pub fn draw_bbox(&self, actor: &Actor, canvas: &mut Canvas, scale: Vec2) {
-        let rect = Self::get_screen_coords(&actor.bbox, &scale);
-        let bbox = match actor.tag {
-            ActorType::Player => &self.player_bbox,
-            ActorType::GroundBlock { x: _, y: _ } => &self.ground_bbox,
-        };
-        canvas.draw(bbox, DrawParam::new().dest(rect.point()).scale(rect.size()));
+    /// Draws a one pixel wide outline of a rectangle given in game space.
+    pub fn draw_outline(&self, rect: &Rect, color: Color, canvas: &mut Canvas) {
+        let r = Self::get_screen_coords(rect);
+        let edges = [
+            Rect::new(r.x, r.y, r.w, 1.0),
+            Rect::new(r.x, r.y + r.h - 1.0, r.w, 1.0),
+            Rect::new(r.x, r.y, 1.0, r.h),
+            Rect::new(r.x + r.w - 1.0, r.y, 1.0, r.h),
+        ];
+        for edge in edges {
+            self.draw_panel(edge, color, canvas);
+        }
+    }
+
+    /// Draws a line from `from` along the vector `vec`, both in game space.
+    pub fn draw_vector(&self, from: Point2, vec: Vec2, color: Color, canvas: &mut Canvas) {
+        let length = vec.length();
+        if length <= 0.0 {
+            return;
+        }
+        // Screen y-axis is inverted, so the angle is mirrored as well
+        let angle = (-vec.y).atan2(vec.x);
+        let start = Self::get_screen_point(from);
+        canvas.draw(
+            &self.line,
+            DrawParam::new()
+                .dest(start)
+                .rotation(angle)
+                .scale(Vec2::new(length, 2.0))
+                .color(color),
+        );
+    }
+
+    /// Draws a filled rectangle, given in screen coordinates.
+    pub fn draw_panel(&self, rect: Rect, color: Color, canvas: &mut Canvas) {
+        canvas.draw(
+            &self.line,
+            DrawParam::new()
+                .dest(Point2::new(rect.x, rect.y + rect.h / 2.0))
+                .scale(Vec2::new(rect.w, rect.h))
+                .color(color),
+        );
+    }
+
+    pub fn draw_tile_grid(&self, canvas: &mut Canvas) {
+        canvas.draw(&self.tile_grid, DrawParam::new());
     }
 }
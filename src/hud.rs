@@ -0,0 +1,126 @@
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawParam, Text, TextAlign, TextLayout};
+
+use crate::constants::SCREEN_WIDTH;
+use crate::game::SpriteGame;
+use crate::player::MAX_HEALTH;
+use crate::primitives::Point2;
+use crate::speedrun::Split;
+
+const TEXT_SIZE: f32 = 24.0;
+const LINE_HEIGHT: f32 = 28.0;
+/// Distance of HUD elements from the screen edges
+const MARGIN: f32 = 12.0;
+/// How long a split time stays on screen, in seconds
+const SPLIT_SHOW_TIME: f32 = 3.0;
+
+/// Position along the top edge of the screen that HUD text is aligned to.
+/// Positions are in the game's virtual resolution, so they stay in place when the window is scaled.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HudAnchor {
+    Left,
+    Center,
+    Right,
+}
+
+impl HudAnchor {
+    /// Returns the anchor point, inset by the margin, in unscaled screen coordinates.
+    fn point(self) -> Point2 {
+        let x = match self {
+            HudAnchor::Left => MARGIN,
+            HudAnchor::Center => SCREEN_WIDTH / 2.0,
+            HudAnchor::Right => SCREEN_WIDTH - MARGIN,
+        };
+        Point2::new(x, MARGIN)
+    }
+
+    /// Text alignment that keeps the text on the anchor's side of the anchor point.
+    fn layout(self) -> TextLayout {
+        let h_align = match self {
+            HudAnchor::Left => TextAlign::Begin,
+            HudAnchor::Center => TextAlign::Middle,
+            HudAnchor::Right => TextAlign::End,
+        };
+        TextLayout {
+            h_align,
+            v_align: TextAlign::Begin,
+        }
+    }
+}
+
+/// Draws the heads-up display on top of the game world.
+pub fn draw_hud(game: &SpriteGame, canvas: &mut Canvas) {
+    let score = &game.score;
+    draw_text(&game.level.name, HudAnchor::Left, 0, canvas);
+    draw_text(&format_time(game.level_time), HudAnchor::Center, 0, canvas);
+    if let Some(split) = game.speedrun.latest_split() {
+        if game.level_time - split.time < SPLIT_SHOW_TIME {
+            draw_text(&format_split(&split), HudAnchor::Center, 1, canvas);
+        }
+    }
+    draw_text(
+        &format!(
+            "Score: {}   Items: {}/{}",
+            score.score, score.collected, score.total
+        ),
+        HudAnchor::Right,
+        0,
+        canvas,
+    );
+    draw_text(
+        &format!("Health: {}/{}", game.player.health, MAX_HEALTH),
+        HudAnchor::Right,
+        1,
+        canvas,
+    );
+}
+
+/// Draws a line of HUD text. `line` is the line number from the anchor.
+pub fn draw_text(text: &str, anchor: HudAnchor, line: usize, canvas: &mut Canvas) {
+    let mut text = Text::new(text);
+    text.set_scale(TEXT_SIZE).set_layout(anchor.layout());
+    let pos = anchor.point() + Vec2::new(0.0, LINE_HEIGHT * line as f32);
+    canvas.draw(&text, DrawParam::new().dest(pos).color(Color::WHITE));
+}
+
+/// Formats a time in seconds as minutes, seconds and tenths (`1:05.3`).
+pub fn format_time(seconds: f32) -> String {
+    let tenths = (seconds.max(0.0) * 10.0) as u32;
+    format!("{}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+}
+
+/// Formats a split time with its difference to the best run (`#2 0:04.0 (-1.0)`).
+fn format_split(split: &Split) -> String {
+    let time = format!("#{} {}", split.number, format_time(split.time));
+    match split.delta {
+        Some(delta) => format!("{} ({:+.1})", time, delta),
+        None => time,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_minutes_seconds_and_tenths() {
+        assert_eq!(format_time(0.0), "0:00.0");
+        assert_eq!(format_time(65.34), "1:05.3");
+        assert_eq!(format_time(600.0), "10:00.0");
+    }
+
+    #[test]
+    fn formats_split_with_delta() {
+        let split = Split {
+            number: 2,
+            time: 4.0,
+            delta: Some(-1.04),
+        };
+        assert_eq!(format_split(&split), "#2 0:04.0 (-1.0)");
+        let first = Split {
+            delta: None,
+            ..split
+        };
+        assert_eq!(format_split(&first), "#2 0:04.0");
+    }
+}
@@ -5,13 +5,146 @@ use ggez::GameResult;
 
 use crate::primitives::Direction;
 
+/// Keys that can be bound to game actions
+const BINDABLE_KEYS: [KeyCode; 50] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Space,
+    KeyCode::Tab,
+    KeyCode::LShift,
+    KeyCode::RShift,
+    KeyCode::LControl,
+    KeyCode::RControl,
+    KeyCode::LAlt,
+    KeyCode::RAlt,
+    KeyCode::Comma,
+    KeyCode::Period,
+];
+
+/// Keys for each game action. Any of an action's keys triggers it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    pub left: Vec<KeyCode>,
+    pub right: Vec<KeyCode>,
+    pub jump: Vec<KeyCode>,
+    pub run: Vec<KeyCode>,
+    pub attack: Vec<KeyCode>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            left: vec![KeyCode::Left, KeyCode::A],
+            right: vec![KeyCode::Right, KeyCode::D],
+            jump: vec![KeyCode::Space, KeyCode::Up, KeyCode::W],
+            run: vec![KeyCode::LShift, KeyCode::RShift],
+            attack: vec![KeyCode::X, KeyCode::LControl],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Action names, as used in the settings file.
+    pub const ACTIONS: [&'static str; 5] = ["left", "right", "jump", "run", "attack"];
+
+    pub fn keys(&self, action: &str) -> Option<&Vec<KeyCode>> {
+        match action {
+            "left" => Some(&self.left),
+            "right" => Some(&self.right),
+            "jump" => Some(&self.jump),
+            "run" => Some(&self.run),
+            "attack" => Some(&self.attack),
+            _ => None,
+        }
+    }
+
+    pub fn keys_mut(&mut self, action: &str) -> Option<&mut Vec<KeyCode>> {
+        match action {
+            "left" => Some(&mut self.left),
+            "right" => Some(&mut self.right),
+            "jump" => Some(&mut self.jump),
+            "run" => Some(&mut self.run),
+            "attack" => Some(&mut self.attack),
+            _ => None,
+        }
+    }
+
+    /// Parses a comma-separated list of key names (`Left,A`).
+    pub fn parse_keys(list: &str) -> Option<Vec<KeyCode>> {
+        let keys: Option<Vec<KeyCode>> = list
+            .split(',')
+            .map(|name| {
+                BINDABLE_KEYS
+                    .into_iter()
+                    .find(|k| format!("{:?}", k) == name.trim())
+            })
+            .collect();
+        keys.filter(|k| !k.is_empty())
+    }
+
+    pub fn format_keys(keys: &[KeyCode]) -> String {
+        keys.iter()
+            .map(|k| format!("{:?}", k))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct InputState {
     pub left: bool,
     pub right: bool,
     pub jump: bool,
-    pub request_quit: bool,
     pub running: bool,
+    pub attack: bool,
+    /// Set by the gameplay scene when an attack key is pressed; cleared when the player's
+    /// input has been handled, so that holding the key attacks only once
+    pub attack_pressed: bool,
+    /// Toggled on and off with F3
+    pub debug_overlay: bool,
+    /// Set when F4 is pressed; cleared when the scale mode has been changed
+    pub cycle_scale_mode: bool,
+    /// Set when F11 is pressed; cleared when the window mode has been changed
+    pub toggle_fullscreen: bool,
+    pub bindings: KeyBindings,
     raw_keys: HashMap<KeyCode, bool>,
 }
 
@@ -26,7 +159,9 @@ impl InputState {
 
     pub fn handle_key_down(&mut self, input: KeyInput) -> GameResult {
         if let Some(key) = input.keycode {
-            self.raw_keys.insert(key, true);
+            if self.raw_keys.insert(key, true).is_none() {
+                self.handle_key_press(key);
+            }
             self.update_state();
         }
         Ok(())
@@ -40,17 +175,22 @@ impl InputState {
         Ok(())
     }
 
+    /// Handles toggle keys; called only once per key press, ignoring key repeats.
+    fn handle_key_press(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::F3 => self.debug_overlay = !self.debug_overlay,
+            KeyCode::F4 => self.cycle_scale_mode = true,
+            KeyCode::F11 => self.toggle_fullscreen = true,
+            _ => {}
+        }
+    }
+
     fn update_state(&mut self) {
-        self.left =
-            self.raw_keys.contains_key(&KeyCode::Left) || self.raw_keys.contains_key(&KeyCode::A);
-        self.right =
-            self.raw_keys.contains_key(&KeyCode::Right) || self.raw_keys.contains_key(&KeyCode::D);
-        self.jump = self.raw_keys.contains_key(&KeyCode::Space)
-            || self.raw_keys.contains_key(&KeyCode::Up)
-            || self.raw_keys.contains_key(&KeyCode::W);
-        self.running = self.raw_keys.contains_key(&KeyCode::LShift)
-            || self.raw_keys.contains_key(&KeyCode::RShift);
-        self.request_quit =
-            self.raw_keys.contains_key(&KeyCode::Escape) || self.raw_keys.contains_key(&KeyCode::Q);
+        let pressed = |keys: &[KeyCode]| keys.iter().any(|k| self.raw_keys.contains_key(k));
+        self.left = pressed(&self.bindings.left);
+        self.right = pressed(&self.bindings.right);
+        self.jump = pressed(&self.bindings.jump);
+        self.attack = pressed(&self.bindings.attack);
+        self.running = pressed(&self.bindings.run);
     }
 }
@@ -0,0 +1,320 @@
+//! Checks level files for mistakes that would crash the game or make the level
+//! impossible to finish. Used by the `level-check` binary.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+use ggez::GameError;
+
+use crate::actor::Actor;
+use crate::autotile::{autotile, Neighborhood};
+use crate::constants::{GROUND_TILE_HEIGHT, GROUND_TILE_WIDTH, LEVEL_HEIGHT, LEVEL_WIDTH};
+use crate::entity::Behavior;
+use crate::level_handler::{LevelHandler, LevelTiles};
+use crate::parallax::ParallaxLayer;
+use crate::player::START_POSITION;
+use crate::primitives::Direction;
+
+/// Ground tileset, relative to the working directory
+pub const TILESET_FILE: &str = "resources/background-tileset.png";
+
+/// A mistake found in a level file.
+#[derive(Debug, PartialEq)]
+pub struct Problem {
+    /// Line and column in the level file, starting from 1
+    pub position: Option<(usize, usize)>,
+    pub message: String,
+}
+
+impl Problem {
+    fn at(line: usize, column: usize, message: String) -> Problem {
+        Problem {
+            position: Some((line, column)),
+            message,
+        }
+    }
+
+    fn new(message: String) -> Problem {
+        Problem {
+            position: None,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.position {
+            Some((line, column)) => write!(f, "{}:{}: {}", line, column, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// Checks a level file, and the background layers file next to it.
+/// Returns an error if one of the files, or the tileset, cannot be read.
+pub fn check_level(file: &Path, tileset: &Path) -> io::Result<Vec<Problem>> {
+    let text = fs::read_to_string(file)?;
+    let (width, height) = png_size(tileset)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", tileset.display(), e)))?;
+    let tileset_size = (
+        (width as f32 / GROUND_TILE_WIDTH) as usize,
+        (height as f32 / GROUND_TILE_HEIGHT) as usize,
+    );
+    let mut problems = check_text(&file.to_string_lossy(), &text, tileset_size);
+    match ParallaxLayer::load(&file.with_extension("layers")) {
+        Ok(_) => {}
+        Err(GameError::IOError(e)) => return Err(io::Error::new(e.kind(), e.to_string())),
+        Err(e) => problems.push(Problem::new(format!("invalid background layers: {}", e))),
+    }
+    Ok(problems)
+}
+
+/// Checks the contents of a level file. `tileset_size` is the size of the ground
+/// tileset in tiles.
+pub fn check_text(file: &str, text: &str, tileset_size: (usize, usize)) -> Vec<Problem> {
+    let tiles = LevelTiles::new();
+    let lines: Vec<&str> = text.lines().collect();
+    let mut problems = check_characters(&lines, &tiles);
+    // The level cannot be built from unknown characters
+    if !problems.is_empty() {
+        return problems;
+    }
+
+    let rows: Vec<String> = lines
+        .iter()
+        .take(LEVEL_HEIGHT)
+        .map(|l| l.to_string())
+        .collect();
+    problems.extend(check_tileset(&rows, &tiles, tileset_size));
+
+    let level = LevelHandler::from_rows(file, rows, LEVEL_WIDTH, LEVEL_HEIGHT, Vec::new());
+    problems.extend(check_spawns(&level));
+    problems
+}
+
+/// Reports unknown characters, rows that are shorter than the widest row,
+/// and rows and lines that do not fit in the level.
+fn check_characters(lines: &[&str], tiles: &LevelTiles) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let rows = &lines[..lines.len().min(LEVEL_HEIGHT)];
+    let widest = rows.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let width = widest.min(LEVEL_WIDTH);
+    for (i, line) in lines.iter().enumerate() {
+        for (column, c) in line.chars().enumerate() {
+            if !tiles.is_level_char(c) {
+                let message = format!("unknown character '{}'", c);
+                problems.push(Problem::at(i + 1, column + 1, message));
+            }
+        }
+        let length = line.chars().count();
+        if length > LEVEL_WIDTH {
+            let message = format!(
+                "row is {} characters long; characters past column {} are ignored",
+                length, LEVEL_WIDTH
+            );
+            problems.push(Problem::at(i + 1, LEVEL_WIDTH + 1, message));
+        } else if length < width && i < LEVEL_HEIGHT {
+            let message = format!(
+                "row is {} characters long, but the widest row is {}",
+                length, width
+            );
+            problems.push(Problem::at(i + 1, length + 1, message));
+        }
+    }
+    if lines.len() > LEVEL_HEIGHT {
+        let message = format!(
+            "level has {} rows; rows after row {} are ignored",
+            lines.len(),
+            LEVEL_HEIGHT
+        );
+        problems.push(Problem::at(LEVEL_HEIGHT + 1, 1, message));
+    }
+    problems
+}
+
+/// Reports tile types whose sprite is outside the tileset, once per tile type.
+fn check_tileset(rows: &[String], tiles: &LevelTiles, size: (usize, usize)) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let mut reported = HashSet::new();
+    let rows = autotile(rows, LEVEL_WIDTH, Neighborhood::Four, false);
+    for (i, row) in rows.iter().enumerate() {
+        for (column, c) in row.chars().enumerate() {
+            let Some(tile) = tiles.get(c) else {
+                continue;
+            };
+            if (tile.x >= size.0 || tile.y >= size.1) && reported.insert(c) {
+                let message = format!(
+                    "tile {} is at ({}, {}), outside the {}x{} tileset",
+                    tile.name, tile.x, tile.y, size.0, size.1
+                );
+                problems.push(Problem::at(i + 1, column + 1, message));
+            }
+        }
+    }
+    problems
+}
+
+/// Checks the player start position, and that the collectibles and enemies can be reached from it.
+/// Reachable means connected to the player start through cells that are not solid,
+/// counting breakable blocks as open; jump heights are not taken into account.
+fn check_spawns(level: &LevelHandler) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let rows = level.row_count();
+    // Converts tile indices to a level file position
+    let position = |x: usize, y: usize| (rows - y, x + 1);
+
+    let player = Actor::create_knight(START_POSITION, Direction::Right);
+    if level.collides_with(&player.bbox) {
+        problems.push(Problem::new(String::from(
+            "player start position is inside solid ground",
+        )));
+    }
+    if level.collectible_spawns.is_empty() {
+        problems.push(Problem::new(String::from(
+            "level has no collectibles; a level is finished by collecting all of them",
+        )));
+    }
+
+    let solid: HashSet<(usize, usize)> = level
+        .tiles
+        .iter()
+        .filter(|t| t.behavior == Behavior::Solid)
+        .map(|t| {
+            let pos = t.actor.pos;
+            (
+                (pos.x / GROUND_TILE_WIDTH) as usize,
+                (pos.y / GROUND_TILE_HEIGHT) as usize,
+            )
+        })
+        .collect();
+    let start = (
+        (START_POSITION.x / GROUND_TILE_WIDTH) as usize,
+        (START_POSITION.y / GROUND_TILE_HEIGHT) as usize,
+    );
+    let reachable = open_area(start, &solid, (LEVEL_WIDTH, rows));
+    let spawns = level
+        .collectible_spawns
+        .iter()
+        .map(|&(kind, x, y)| (format!("{:?}", kind), x, y))
+        .chain(
+            level
+                .enemy_spawns
+                .iter()
+                .map(|&(x, y)| (String::from("Enemy"), x, y)),
+        );
+    for (name, x, y) in spawns {
+        if !reachable.contains(&(x, y)) {
+            let (line, column) = position(x, y);
+            let message = format!("{} cannot be reached from the player start", name);
+            problems.push(Problem::at(line, column, message));
+        }
+    }
+    problems
+}
+
+/// Returns the cells that are connected to `start` through cells that are not solid.
+fn open_area(
+    start: (usize, usize),
+    solid: &HashSet<(usize, usize)>,
+    size: (usize, usize),
+) -> HashSet<(usize, usize)> {
+    let mut open = HashSet::new();
+    let mut queue = vec![start];
+    while let Some((x, y)) = queue.pop() {
+        if x >= size.0 || y >= size.1 || solid.contains(&(x, y)) || !open.insert((x, y)) {
+            continue;
+        }
+        queue.push((x + 1, y));
+        queue.push((x, y + 1));
+        // Wrapping keeps the cell out of bounds, so it is skipped
+        queue.push((x.wrapping_sub(1), y));
+        queue.push((x, y.wrapping_sub(1)));
+    }
+    open
+}
+
+/// Reads the image size from the header of a PNG file.
+fn png_size(path: &Path) -> io::Result<(u32, u32)> {
+    let mut header = [0; 24];
+    File::open(path)?.read_exact(&mut header)?;
+    if &header[1..4] != b"PNG" || &header[12..16] != b"IHDR" {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a PNG file"));
+    }
+    let width = u32::from_be_bytes([header[16], header[17], header[18], header[19]]);
+    let height = u32::from_be_bytes([header[20], header[21], header[22], header[23]]);
+    Ok((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILESET: (usize, usize) = (12, 8);
+
+    /// A level with the player start open, a ledge above it, and the given rows on top.
+    fn level(top: &[&str]) -> String {
+        let mut lines: Vec<String> = top.iter().map(|l| pad(l)).collect();
+        while lines.len() < LEVEL_HEIGHT - 1 {
+            lines.push(pad(""));
+        }
+        lines.push("X".repeat(LEVEL_WIDTH));
+        lines.join("\n")
+    }
+
+    fn pad(line: &str) -> String {
+        format!("{:1$}", line, LEVEL_WIDTH)
+    }
+
+    #[test]
+    fn accepts_valid_level() {
+        assert_eq!(check_text("test", &level(&["o  e"]), TILESET), vec![]);
+    }
+
+    #[test]
+    fn reports_unknown_characters_and_long_rows() {
+        let long = "#".repeat(LEVEL_WIDTH + 1);
+        let problems = check_text("test", &level(&["o ?", &long]), TILESET);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].position, Some((1, 3)));
+        assert_eq!(problems[1].position, Some((2, LEVEL_WIDTH + 1)));
+    }
+
+    #[test]
+    fn reports_ragged_rows() {
+        let mut text = level(&["o"]);
+        text = text.replacen(&pad("o"), "o", 1);
+        let problems = check_text("test", &text, TILESET);
+        let messages: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![format!(
+                "1:2: row is 1 characters long, but the widest row is {}",
+                LEVEL_WIDTH
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_enclosed_spawns_and_missing_collectibles() {
+        let problems = check_text("test", &level(&["┌^┐", "<e>", "└v┘"]), TILESET);
+        let messages: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "level has no collectibles; a level is finished by collecting all of them",
+                "2:2: Enemy cannot be reached from the player start",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_tiles_outside_tileset() {
+        let problems = check_text("test", &level(&["o"]), (1, 1));
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].message.starts_with("tile TOP"));
+    }
+}
@@ -2,34 +2,240 @@ use std::collections::HashMap;
 use std::fs::File;
 use std::io;
 use std::io::BufRead;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use ggez::graphics::Rect;
-use ggez::GameResult;
+use ggez::{GameError, GameResult};
 
 use crate::actor::Actor;
+use crate::animation_handler::AnimationLibrary;
+use crate::autotile::{autotile, Neighborhood, AUTOTILE_CHAR};
+use crate::collectible::{create_collectible, CollectibleKind};
 use crate::constants::{GROUND_TILE_HEIGHT, GROUND_TILE_WIDTH};
-use crate::primitives::RectExt;
+use crate::enemy::create_enemy;
+use crate::entity::{Behavior, Entity, EntityStore, RenderLayer, SpriteSource};
+use crate::parallax::ParallaxLayer;
+use crate::persist;
+use crate::primitives::{Point2, RectExt};
 
+/// Level file character for enemy start positions
+const ENEMY_CHAR: char = 'e';
+/// Level file character for blocks that can be broken by attacking them
+const BREAKABLE_CHAR: char = 'B';
+/// Level file character for hanging moss, drawn in front of the player
+const MOSS_CHAR: char = '~';
+/// Level file character for small stalactites, drawn behind the player
+const STALACTITE_CHAR: char = ':';
+/// Level file characters for objects that are placed on top of empty tiles
+const OBJECT_CHARS: [char; 3] = ['o', '*', ENEMY_CHAR];
+
+/// Holds the level geometry, and the start positions of the level's dynamic entities.
 pub struct LevelHandler {
-    pub actors: Vec<Actor>,
+    /// Level file name without the extension
+    pub name: String,
+    path: PathBuf,
+    /// Lines of the level file, top row first. Kept as they were read, so that
+    /// saving an edited level only changes the edited characters.
+    rows: Vec<String>,
+    /// Level width in tiles
+    width: usize,
+    /// Level tiles; these are static, but breakable tiles can be removed
+    pub tiles: Vec<Entity>,
+    /// Collectible positions, as (x, y) tile indices
+    pub collectible_spawns: Vec<(CollectibleKind, usize, usize)>,
+    /// Enemy start positions, as (x, y) tile indices
+    pub enemy_spawns: Vec<(usize, usize)>,
+    /// Background layers, back to front
+    pub background: Vec<ParallaxLayer>,
     bbox: Rect,
+    /// Incremented whenever tiles are removed or restored
+    revision: u64,
+    /// Maximum number of rows read from the level file
+    height: usize,
+    /// Set when the level has been edited since it was read or saved
+    modified: bool,
 }
 
 impl LevelHandler {
     pub fn new(file: &str, width: usize, height: usize) -> GameResult<LevelHandler> {
-        let tiles = LevelTiles::new();
-        let level = LevelBuilder::load_level(file, &tiles, width, height)?;
-        let actors = LevelBuilder::create_actors(&level);
-        Ok(LevelHandler {
-            actors,
+        let rows = LevelBuilder::read_lines(file, height)?;
+        LevelBuilder::check_chars(file, &rows)?;
+        // Background layers are defined in a file next to the level file
+        let background = ParallaxLayer::load(&Path::new(file).with_extension("layers"))?;
+        Ok(Self::from_rows(file, rows, width, height, background))
+    }
+
+    /// Creates a level from the lines of a level file.
+    /// Panics if the lines contain characters that are not level characters.
+    pub fn from_rows(
+        file: &str,
+        rows: Vec<String>,
+        width: usize,
+        height: usize,
+        background: Vec<ParallaxLayer>,
+    ) -> LevelHandler {
+        let name = Path::new(file)
+            .file_stem()
+            .map_or(String::new(), |s| s.to_string_lossy().to_string());
+        let mut level = LevelHandler {
+            name,
+            path: PathBuf::from(file),
+            rows,
+            width,
+            tiles: Vec::new(),
+            collectible_spawns: Vec::new(),
+            enemy_spawns: Vec::new(),
+            background,
             bbox: Rect {
                 x: 0.0,
                 y: 0.0,
                 w: width as f32 * GROUND_TILE_WIDTH,
                 h: height as f32 * GROUND_TILE_HEIGHT,
             },
-        })
+            revision: 0,
+            height,
+            modified: false,
+        };
+        level.build();
+        level
+    }
+
+    /// Creates the level tiles and object start positions from the level file rows.
+    fn build(&mut self) {
+        let tiles = LevelTiles::new();
+        let rows = autotile(&self.rows, self.width, Neighborhood::Four, false);
+        let level = LevelBuilder::create_level(&rows, &tiles, self.width);
+        self.tiles = LevelBuilder::create_tiles(&level);
+        self.collectible_spawns = LevelBuilder::find_collectibles(&self.rows, self.width);
+        self.enemy_spawns = LevelBuilder::find_chars(&self.rows, self.width, ENEMY_CHAR);
+        self.revision += 1;
+    }
+
+    /// Returns the (x, y) tile indices of the tile at a point in game space,
+    /// or `None` if the point is outside the level.
+    pub fn tile_at(&self, point: Point2) -> Option<(usize, usize)> {
+        if point.x < 0.0 || point.y < 0.0 {
+            return None;
+        }
+        let x = (point.x / GROUND_TILE_WIDTH) as usize;
+        let y = (point.y / GROUND_TILE_HEIGHT) as usize;
+        (x < self.width && y < self.rows.len()).then_some((x, y))
+    }
+
+    /// Number of rows in the level file; tile y indices are counted up from the last row.
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the level file character of a tile, or a space for tiles past the end of a row.
+    pub fn char_at(&self, x: usize, y: usize) -> char {
+        let row = &self.rows[self.rows.len() - y - 1];
+        row.chars().nth(x).unwrap_or(' ')
+    }
+
+    /// Changes the level file character of a tile, and rebuilds the level.
+    /// Returns false if the tile already had the character.
+    pub fn set_char(&mut self, x: usize, y: usize, char: char) -> bool {
+        if self.char_at(x, y) == char {
+            return false;
+        }
+        let index = self.rows.len() - y - 1;
+        let mut row: Vec<char> = self.rows[index].chars().collect();
+        if row.len() <= x {
+            row.resize(x + 1, ' ');
+        }
+        row[x] = char;
+        self.rows[index] = row.into_iter().collect::<String>().trim_end().to_string();
+        self.modified = true;
+        self.build();
+        true
+    }
+
+    /// Returns true if the level has been edited since it was read or saved.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// Reads the level again from its files, for example after they have been changed on disk.
+    /// The returned level is meant to replace this one.
+    pub fn reload(&self) -> GameResult<LevelHandler> {
+        let file = self.path.to_string_lossy().to_string();
+        let mut level = LevelHandler::new(&file, self.width, self.height)?;
+        // Keeps the revision increasing, so that cached tile graphics are rebuilt
+        level.revision += self.revision + 1;
+        Ok(level)
+    }
+
+    /// Files that the level is read from: the level file and its background layers.
+    pub fn files(&self) -> Vec<PathBuf> {
+        vec![self.path.clone(), self.path.with_extension("layers")]
+    }
+
+    /// Picks all ground tiles of the level again from their neighbors, and rebuilds the level.
+    pub fn autotile(&mut self, neighborhood: Neighborhood) {
+        self.rows = autotile(&self.rows, self.width, neighborhood, true)
+            .into_iter()
+            .map(|r| r.trim_end().to_string())
+            .collect();
+        self.modified = true;
+        self.build();
+    }
+
+    /// Writes the level back to its level file. Rows are padded with spaces to the widest row,
+    /// so that the file passes the level check for ragged rows.
+    pub fn save(&mut self) -> GameResult {
+        let width = self
+            .rows
+            .iter()
+            .map(|r| r.chars().count())
+            .max()
+            .unwrap_or(0);
+        let text: String = self
+            .rows
+            .iter()
+            .map(|row| format!("{:1$}\n", row, width))
+            .collect();
+        persist::write_atomic(&self.path, &text)?;
+        self.modified = false;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Restores removed tiles, and adds the level's collectibles and enemies to the entity store.
+    pub fn reset(&mut self, entities: &mut EntityStore, animations: &AnimationLibrary) {
+        for t in &mut self.tiles {
+            t.alive = true;
+        }
+        self.revision += 1;
+        self.spawn_entities(entities, animations, |_| false);
+    }
+
+    /// Adds the level's collectibles and enemies to the entity store, leaving out
+    /// the collectibles whose spawn tile is reported as already collected.
+    pub fn spawn_entities<F>(
+        &self,
+        entities: &mut EntityStore,
+        animations: &AnimationLibrary,
+        is_collected: F,
+    ) where
+        F: Fn((usize, usize)) -> bool,
+    {
+        for &(kind, x, y) in &self.collectible_spawns {
+            if !is_collected((x, y)) {
+                entities.spawn(create_collectible(kind, x, y));
+            }
+        }
+        for &(x, y) in &self.enemy_spawns {
+            entities.spawn(create_enemy(x, y, animations));
+        }
+    }
+
+    /// Level area in game space.
+    pub fn bounds(&self) -> Rect {
+        self.bbox
     }
 
     pub fn get_collisions(&self, bbox: &Rect) -> Vec<&Actor> {
@@ -37,18 +243,40 @@ impl LevelHandler {
             return Vec::new();
         }
 
-        self.actors
+        self.tiles
             .iter()
-            .filter(|a| a.bbox.collides_with(bbox))
+            .filter(|t| t.alive && t.behavior.is_solid() && t.actor.bbox.collides_with(bbox))
+            .map(|t| &t.actor)
             .collect()
     }
 
+    /// Removes all attackable tiles overlapping the given area.
+    /// Returns the number of removed tiles.
+    pub fn break_tiles(&mut self, area: &Rect) -> usize {
+        let mut count = 0;
+        for t in &mut self.tiles {
+            if t.alive && t.behavior.is_attackable() && t.actor.bbox.collides_with(area) {
+                t.alive = false;
+                count += 1;
+            }
+        }
+        if count > 0 {
+            self.revision += 1;
+        }
+        count
+    }
+
+    /// Changes whenever the level's tiles change, so that cached tile graphics can be rebuilt.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
     pub fn collides_with(&self, bbox: &Rect) -> bool {
         if !self.bbox.collides_with(bbox) {
             return false;
         }
-        for a in &self.actors {
-            if a.bbox.collides_with(bbox) {
+        for t in &self.tiles {
+            if t.alive && t.behavior.is_solid() && t.actor.bbox.collides_with(bbox) {
                 return true;
             }
         }
@@ -59,51 +287,116 @@ impl LevelHandler {
 struct LevelBuilder {}
 
 impl LevelBuilder {
-    fn load_level(
-        file: &str,
-        tiles: &LevelTiles,
-        width: usize,
-        height: usize,
-    ) -> GameResult<Vec<Vec<TileType>>> {
+    fn read_lines(file: &str, height: usize) -> GameResult<Vec<String>> {
         let path = Path::new(file);
         let file = File::open(path)?;
         let reader = io::BufReader::new(file);
 
-        let level = reader
+        let rows = reader
             .lines()
             .take(height)
             .map(|r| r.unwrap_or(String::from("")))
-            .map(|r| Self::read_row(tiles, &r, width))
             .collect();
 
-        Ok(level)
+        Ok(rows)
+    }
+
+    /// Returns an error for the first character that has no meaning in level files.
+    fn check_chars(file: &str, rows: &[String]) -> GameResult {
+        let tiles = LevelTiles::new();
+        for (line, row) in rows.iter().enumerate() {
+            if let Some(column) = row.chars().position(|c| !tiles.is_level_char(c)) {
+                return Err(GameError::ResourceLoadError(format!(
+                    "{}:{}:{}: unknown level character",
+                    file,
+                    line + 1,
+                    column + 1
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn create_level(rows: &[String], tiles: &LevelTiles, width: usize) -> Vec<Vec<TileType>> {
+        rows.iter()
+            .map(|r| Self::read_row(tiles, r, width))
+            .collect()
+    }
+
+    /// Returns the (x, y) tile indices of all occurrences of the given character.
+    fn find_chars(rows: &[String], width: usize, char: char) -> Vec<(usize, usize)> {
+        let height = rows.len();
+        let mut found = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().take(width).enumerate() {
+                if c == char {
+                    found.push((x, height - y - 1));
+                }
+            }
+        }
+        found
+    }
+
+    fn find_collectibles(rows: &[String], width: usize) -> Vec<(CollectibleKind, usize, usize)> {
+        let height = rows.len();
+        let mut collectibles = Vec::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().take(width).enumerate() {
+                if let Some(kind) = CollectibleKind::for_char(c) {
+                    collectibles.push((kind, x, height - y - 1));
+                }
+            }
+        }
+        collectibles
     }
 
-    fn create_actors(level: &[Vec<TileType>]) -> Vec<Actor> {
+    fn create_tiles(level: &[Vec<TileType>]) -> Vec<Entity> {
         let height = level.len();
-        let mut actors = Vec::new();
+        let mut tiles = Vec::new();
         for (y, row) in level.iter().enumerate() {
             for (x, tile) in row.iter().enumerate() {
-                if let Some(actor) = LevelBuilder::create_tile(tile, x, height - y - 1) {
-                    actors.push(actor)
+                if let Some(entity) = LevelBuilder::create_tile(tile, x, height - y - 1) {
+                    tiles.push(entity)
                 }
             }
         }
-        actors
+        tiles
     }
 
-    fn create_tile(tile: &TileType, x: usize, y: usize) -> Option<Actor> {
-        match tile.char {
-            ' ' => None,
-            _ => Some(Actor::create_ground(tile, x, y)),
-        }
+    fn create_tile(tile: &TileType, x: usize, y: usize) -> Option<Entity> {
+        let behavior = match tile.char {
+            ' ' => return None,
+            BREAKABLE_CHAR => Behavior::Breakable,
+            MOSS_CHAR | STALACTITE_CHAR => Behavior::Decoration,
+            _ => Behavior::Solid,
+        };
+        let sprite = SpriteSource::Tile {
+            x: tile.x,
+            y: tile.y,
+        };
+        let layer = match tile.char {
+            MOSS_CHAR => RenderLayer::Foreground,
+            _ => behavior.render_layer(),
+        };
+        Some(Entity {
+            layer,
+            ..Entity::new(Actor::create_ground(x, y), sprite, behavior)
+        })
+    }
+
+    fn is_object(c: char) -> bool {
+        OBJECT_CHARS.contains(&c)
     }
 
     fn read_row(tiles: &LevelTiles, line: &str, width: usize) -> Vec<TileType> {
         let mut row: Vec<TileType> = line
             .chars()
             .take(width) // Ensure we only process up to `width` characters
-            .map(|c| tiles.for_char(c))
+            .map(|c| match Self::is_object(c) {
+                // Collectibles and enemies are placed on top of empty tiles
+                true => tiles.empty,
+                false => tiles.for_char(c),
+            })
             .collect();
 
         // If the length of the row is less than width, append empty tiles
@@ -117,8 +410,8 @@ impl LevelBuilder {
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct TileType {
-    name: &'static str,
-    char: char,
+    pub name: &'static str,
+    pub char: char,
     /// Tile x index in the tile set; from upper left corner, starting at 0.
     pub x: usize,
     /// Tile y index in the tile set; from upper left corner, starting at 0.
@@ -133,6 +426,8 @@ impl TileType {
 
 pub struct LevelTiles {
     empty: TileType,
+    /// Tile types in the order they are listed in the level editor
+    types: Vec<TileType>,
     tile_map: HashMap<char, TileType>,
 }
 
@@ -157,17 +452,43 @@ impl LevelTiles {
             TileType::new("TOP_RIGHT_LEFT", '┬', 4, 0),
             TileType::new("BOTTOM_RIGHT_LEFT", '┴', 8, 4),
             TileType::new("ALL_BORDERS", '┼', 5, 2),
+            TileType::new("BREAKABLE", BREAKABLE_CHAR, 2, 2),
+            TileType::new("MOSS", MOSS_CHAR, 8, 2),
+            TileType::new("STALACTITE", STALACTITE_CHAR, 7, 2),
         ];
 
-        let tile_map: HashMap<char, TileType> = tile_types
-            .into_iter()
-            .map(|tile| (tile.char, tile))
-            .collect();
-        LevelTiles { empty, tile_map }
+        let tile_map: HashMap<char, TileType> =
+            tile_types.iter().map(|tile| (tile.char, *tile)).collect();
+        LevelTiles {
+            empty,
+            types: tile_types,
+            tile_map,
+        }
     }
 
     pub fn for_char(&self, char: char) -> TileType {
-        let tile = self.tile_map.get(&char).expect("Unknown tile type");
-        *tile
+        self.get(char).expect("Unknown tile type")
+    }
+
+    /// Returns the tile type of a level file character, or `None` for objects and unknown characters.
+    pub fn get(&self, char: char) -> Option<TileType> {
+        self.tile_map.get(&char).copied()
+    }
+
+    /// Returns true if the character has a meaning in level files.
+    pub fn is_level_char(&self, char: char) -> bool {
+        self.tile_map.contains_key(&char) || char == AUTOTILE_CHAR || OBJECT_CHARS.contains(&char)
+    }
+
+    /// Level file characters that can be placed in the level editor: the tiles,
+    /// automatically picked ground, and then the objects.
+    pub fn palette(&self) -> Vec<char> {
+        self.types
+            .iter()
+            .map(|t| t.char)
+            .filter(|&c| c != self.empty.char)
+            .chain([AUTOTILE_CHAR])
+            .chain(OBJECT_CHARS)
+            .collect()
     }
 }
@@ -0,0 +1,83 @@
+//! Sprite Knight, a 2D platformer. The game is started with `run`; the level tools
+//! in `src/bin` use the modules exported here.
+
+use ggez::event::{self};
+use ggez::{conf, ContextBuilder};
+
+use crate::constants::{RESOURCE_DIR, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::game::SpriteGame;
+use crate::scene::SceneStack;
+use crate::scenes::TitleScene;
+
+mod actor;
+mod animation_handler;
+mod animator;
+mod atlas;
+mod audio;
+pub mod autotile;
+mod collectible;
+mod collision;
+mod constants;
+mod debug_overlay;
+mod editor;
+mod enemy;
+mod entity;
+mod event_handler;
+mod file_watch;
+mod game;
+mod game_assets;
+mod game_gfx;
+mod hud;
+mod input_handler;
+pub mod level_check;
+mod level_handler;
+mod menu;
+mod parallax;
+mod persist;
+mod physics;
+mod player;
+mod primitives;
+mod save_game;
+mod scene;
+mod scenes;
+mod settings;
+mod speedrun;
+mod viewport;
+
+/// Command line flag for development mode, where changed images are reloaded while the game runs
+const DEV_FLAG: &str = "--dev";
+
+/// Creates the game window, and runs the game until it is closed.
+pub fn run() {
+    // Make a Context.
+    let (mut ctx, event_loop) = ContextBuilder::new("sprite_game", "Tuukka Haapasalo")
+        .window_setup(conf::WindowSetup::default().title("Sprite Knight"))
+        .window_mode(
+            conf::WindowMode::default()
+                .dimensions(SCREEN_WIDTH, SCREEN_HEIGHT)
+                .resizable(true),
+        )
+        .add_resource_path(RESOURCE_DIR)
+        .build()
+        .expect("Could not create ggez context!");
+
+    println!("Game resource path: {:?}", ctx.fs);
+
+    // Create an instance of your event handler.
+    // Usually, you should provide it with the Context object to
+    // use when setting your game up.
+    let mut sprite_knight = SpriteGame::new(&mut ctx).expect("Could not initialize game");
+    if std::env::args().any(|arg| arg == DEV_FLAG) {
+        println!("Development mode: changed images are reloaded");
+        sprite_knight.gfx.assets.enable_hot_reload();
+    }
+
+    println!("Starting Sprite Knight game loop");
+
+    // Run!
+    event::run(
+        ctx,
+        event_loop,
+        SceneStack::new(sprite_knight, Box::new(TitleScene::new())),
+    );
+}
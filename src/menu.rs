@@ -0,0 +1,90 @@
+use ggez::graphics::{Canvas, Color, DrawParam, Rect, Text, TextLayout};
+use ggez::input::keyboard::KeyCode;
+
+use crate::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::game_gfx::GraphicsHandler;
+use crate::primitives::Point2;
+
+const TITLE_SIZE: f32 = 56.0;
+const ITEM_SIZE: f32 = 30.0;
+const ITEM_SPACING: f32 = 44.0;
+const SELECTED_COLOR: Color = Color::new(1.0, 0.85, 0.3, 1.0);
+
+/// What the player did in a menu.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MenuAction {
+    None,
+    /// Enter was pressed on an item
+    Select(usize),
+    /// Left (-1) or right (1) was pressed on an item
+    Adjust(usize, i32),
+    /// Escape was pressed
+    Back,
+}
+
+/// A vertical list of items, with one item selected.
+/// Item labels are given when drawing, so they can show current values.
+#[derive(Debug)]
+pub struct Menu {
+    title: String,
+    item_count: usize,
+    selected: usize,
+}
+
+impl Menu {
+    pub fn new(title: &str, item_count: usize) -> Menu {
+        Menu {
+            title: title.to_string(),
+            item_count,
+            selected: 0,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) -> MenuAction {
+        match key {
+            KeyCode::Up | KeyCode::W => {
+                self.selected = (self.selected + self.item_count - 1) % self.item_count;
+                MenuAction::None
+            }
+            KeyCode::Down | KeyCode::S => {
+                self.selected = (self.selected + 1) % self.item_count;
+                MenuAction::None
+            }
+            KeyCode::Left | KeyCode::A => MenuAction::Adjust(self.selected, -1),
+            KeyCode::Right | KeyCode::D => MenuAction::Adjust(self.selected, 1),
+            KeyCode::Return | KeyCode::NumpadEnter => MenuAction::Select(self.selected),
+            KeyCode::Escape => MenuAction::Back,
+            _ => MenuAction::None,
+        }
+    }
+
+    /// Draws the menu centered on the screen, over a dimmed backdrop.
+    pub fn draw(&self, gfx: &GraphicsHandler, items: &[String], canvas: &mut Canvas) {
+        let screen = Rect::new(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT);
+        gfx.draw_panel(screen, Color::new(0.0, 0.0, 0.0, 0.6), canvas);
+
+        let height = ITEM_SPACING * items.len() as f32;
+        let top = (SCREEN_HEIGHT - height) / 2.0;
+        draw_centered(&self.title, TITLE_SIZE, top - 80.0, Color::WHITE, canvas);
+        for (i, item) in items.iter().enumerate() {
+            let (label, color) = match i == self.selected {
+                true => (format!("> {} <", item), SELECTED_COLOR),
+                false => (item.clone(), Color::WHITE),
+            };
+            let y = top + i as f32 * ITEM_SPACING;
+            draw_centered(&label, ITEM_SIZE, y, color, canvas);
+        }
+    }
+}
+
+/// Draws a line of text, centered horizontally on the screen at the given y coordinate.
+pub fn draw_centered(text: &str, size: f32, y: f32, color: Color, canvas: &mut Canvas) {
+    let mut text = Text::new(text);
+    text.set_scale(size).set_layout(TextLayout::center());
+    canvas.draw(
+        &text,
+        DrawParam::new()
+            .dest(Point2::new(SCREEN_WIDTH / 2.0, y))
+            .color(color),
+    );
+}
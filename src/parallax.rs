@@ -0,0 +1,123 @@
+use std::fs::File;
+use std::io;
+use std::io::BufRead;
+use std::path::Path;
+
+use ggez::{GameError, GameResult};
+
+/// Background image used when a level does not define its own layers.
+const DEFAULT_BACKGROUND: &str = "/background.png";
+
+/// How a background layer is repeated horizontally.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Repeat {
+    /// Drawn once
+    None,
+    /// Tiled to cover the width of the screen
+    X,
+}
+
+/// How a background layer is sized and placed vertically.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Anchor {
+    /// Scaled to the height of the screen
+    Fill,
+    /// Unscaled, aligned to the top of the screen
+    Top,
+    /// Unscaled, aligned to the bottom of the screen
+    Bottom,
+}
+
+/// A background image layer, scrolled at a fraction of the level's scroll speed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParallaxLayer {
+    /// Image path, relative to the resources directory
+    pub image: String,
+    /// How far the layer moves relative to the level; 0 is fixed, 1 moves with the tiles
+    pub parallax: f32,
+    pub repeat: Repeat,
+    pub anchor: Anchor,
+}
+
+impl ParallaxLayer {
+    /// Loads a level's background layers, back to front. Levels without a layer file
+    /// get a single fixed background.
+    pub fn load(file: &Path) -> GameResult<Vec<ParallaxLayer>> {
+        if !file.exists() {
+            return Ok(vec![ParallaxLayer {
+                image: DEFAULT_BACKGROUND.to_string(),
+                parallax: 0.0,
+                repeat: Repeat::None,
+                anchor: Anchor::Fill,
+            }]);
+        }
+        let reader = io::BufReader::new(File::open(file)?);
+        let mut layers = Vec::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let layer = Self::parse(line).map_err(|e| {
+                GameError::ResourceLoadError(format!("{}:{}: {}", file.display(), index + 1, e))
+            })?;
+            layers.push(layer);
+        }
+        Ok(layers)
+    }
+
+    /// Parses a layer line (`image parallax repeat anchor`).
+    fn parse(line: &str) -> Result<ParallaxLayer, String> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [image, parallax, repeat, anchor] = fields[..] else {
+            return Err(format!("expected 4 fields, found {}", fields.len()));
+        };
+        let parallax = parallax
+            .parse()
+            .map_err(|_| format!("invalid parallax {}", parallax))?;
+        let repeat = match repeat {
+            "none" => Repeat::None,
+            "x" => Repeat::X,
+            _ => return Err(format!("unknown repeat mode {}", repeat)),
+        };
+        let anchor = match anchor {
+            "fill" => Anchor::Fill,
+            "top" => Anchor::Top,
+            "bottom" => Anchor::Bottom,
+            _ => return Err(format!("unknown anchor {}", anchor)),
+        };
+        Ok(ParallaxLayer {
+            image: image.to_string(),
+            parallax,
+            repeat,
+            anchor,
+        })
+    }
+
+    /// Horizontal offset of the layer, in unscaled screen coordinates, for the given scroll position.
+    pub fn offset(&self, scroll: f32) -> f32 {
+        -scroll * self.parallax
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_layer_line() {
+        let layer = ParallaxLayer::parse("/hills.png  0.25  x  bottom").unwrap();
+        assert_eq!(layer.image, "/hills.png");
+        assert_eq!(layer.parallax, 0.25);
+        assert_eq!(layer.repeat, Repeat::X);
+        assert_eq!(layer.anchor, Anchor::Bottom);
+        assert_eq!(layer.offset(100.0), -25.0);
+    }
+
+    #[test]
+    fn rejects_unknown_anchor() {
+        assert!(ParallaxLayer::parse("/hills.png 0.25 x middle").is_err());
+        assert!(ParallaxLayer::parse("/hills.png 0.25 x").is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use ggez::GameResult;
+
+/// Contents of a versioned data file: an optional `version N` line,
+/// followed by `key value` lines.
+#[derive(Debug, PartialEq)]
+pub struct DataFile {
+    /// Format version, or `None` for files written before versioning
+    pub version: Option<u32>,
+    pub entries: Vec<(String, String)>,
+}
+
+impl DataFile {
+    /// Parses a data file. Empty lines and `#` comments are skipped.
+    pub fn parse(text: &str) -> Result<DataFile, String> {
+        let mut version = None;
+        let mut entries = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            if key == "version" {
+                if version.is_some() || !entries.is_empty() {
+                    return Err(String::from("version must be on the first line"));
+                }
+                let number = value.trim().parse().map_err(|_| "invalid version")?;
+                version = Some(number);
+                continue;
+            }
+            entries.push((key.to_string(), value.trim().to_string()));
+        }
+        Ok(DataFile { version, entries })
+    }
+
+    pub fn to_text(version: u32, entries: &[(String, String)]) -> String {
+        let mut text = format!("version {}\n", version);
+        for (key, value) in entries {
+            text += &format!("{} {}\n", key, value);
+        }
+        text
+    }
+}
+
+/// Reads a data file as text. Returns `None` if the file does not exist.
+pub fn read(path: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes a data file through a temporary file, so that a crash while saving
+/// cannot leave a partially written file behind.
+pub fn write_atomic(path: &Path, text: &str) -> GameResult {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = path.with_extension("tmp");
+    fs::write(&temp, text)?;
+    fs::rename(&temp, path)?;
+    Ok(())
+}
+
+/// Moves an unreadable data file aside, so that it is not overwritten and can be inspected.
+/// Returns the new path of the file.
+pub fn quarantine(path: &Path, reason: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".corrupt");
+    let moved = path.with_file_name(name);
+    match fs::rename(path, &moved) {
+        Ok(()) => println!(
+            "Could not read {:?} ({}), moved it to {:?}",
+            path, reason, moved
+        ),
+        Err(e) => println!("Could not read {:?} ({}) or move it: {}", path, reason, e),
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_versioned_file() {
+        let file = DataFile::parse("# comment\nversion 2\nname some value\n\nflag\n").unwrap();
+        assert_eq!(file.version, Some(2));
+        assert_eq!(
+            file.entries,
+            vec![
+                (String::from("name"), String::from("some value")),
+                (String::from("flag"), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn version_must_come_first() {
+        assert!(DataFile::parse("name value\nversion 2\n").is_err());
+        assert!(DataFile::parse("version two\n").is_err());
+        assert_eq!(DataFile::parse("name value\n").unwrap().version, None);
+    }
+}
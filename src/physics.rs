@@ -0,0 +1,93 @@
+use ggez::glam::Vec2;
+use ggez::graphics::Rect;
+
+use crate::actor::Actor;
+use crate::collision::{find_mtv, Contact};
+use crate::level_handler::LevelHandler;
+use crate::primitives::{Point2, RectExt};
+
+// Speeds are pixels per second
+const GRAVITY: f32 = 2000.0;
+const MAX_VELOCITY_Y: f32 = 1200.0;
+
+/// Physics state of a moving actor that is affected by gravity and
+/// collides with the level tiles.
+#[derive(Debug)]
+pub struct PhysicsBody {
+    pub velocity: Vec2,
+    pub grounded: bool,
+    /// Collision contacts resolved during the last update
+    pub contacts: Vec<Contact>,
+}
+
+impl PhysicsBody {
+    pub fn new() -> PhysicsBody {
+        PhysicsBody {
+            velocity: Vec2::new(0.0, 0.0),
+            grounded: true,
+            contacts: Vec::new(),
+        }
+    }
+
+    /// Moves the actor according to the current velocity, resolves collisions
+    /// with the level, and applies gravity.
+    pub fn update(&mut self, actor: &mut Actor, seconds: f32, level: &LevelHandler) {
+        self.contacts.clear();
+        self.velocity.y = self.velocity.y.max(-MAX_VELOCITY_Y);
+        // Move actor along x
+        Self::move_by(actor, self.velocity.x * seconds, 0.0);
+        // Check for collision on x-axis
+        self.check_collision(actor, true, level);
+        // Move actor along y
+        Self::move_by(actor, 0.0, self.velocity.y * seconds);
+        // Check for collision on y-axis
+        self.check_collision(actor, false, level);
+        // Update gravity (takes effect on next round, will be reset if actor is grounded)
+        self.velocity.y -= GRAVITY * seconds;
+
+        self.grounded = Self::is_grounded(actor, level);
+        if self.grounded {
+            self.velocity.y = 0.0;
+        }
+    }
+
+    /// Returns true if the actor was stopped by a wall during the last update.
+    pub fn hit_wall(&self) -> bool {
+        self.contacts.iter().any(|c| c.normal.x != 0.0)
+    }
+
+    fn move_by(actor: &mut Actor, x: f32, y: f32) {
+        actor.pos.x += x;
+        actor.pos.y += y;
+        actor.update_bbox();
+    }
+
+    fn check_collision(&mut self, actor: &mut Actor, along_x: bool, level: &LevelHandler) {
+        let collisions = level.get_collisions(&actor.bbox);
+        for c in collisions {
+            self.resolve_collision(actor, c, along_x);
+        }
+    }
+
+    fn resolve_collision(&mut self, actor: &mut Actor, other: &Actor, along_x: bool) {
+        if let Some(offs) = find_mtv(&actor.bbox, &other.bbox, self.velocity, along_x) {
+            if let Some(overlap) = actor.bbox.clip(&other.bbox) {
+                self.contacts.push(Contact {
+                    point: Point2::new(overlap.x + overlap.w / 2.0, overlap.y + overlap.h / 2.0),
+                    normal: offs.normalize_or_zero(),
+                });
+            }
+            Self::move_by(actor, offs.x, offs.y)
+        }
+    }
+
+    fn is_grounded(actor: &Actor, level: &LevelHandler) -> bool {
+        let ground_check = Rect {
+            x: actor.bbox.x,
+            y: actor.bbox.y - 1.0,
+            w: actor.bbox.w,
+            h: 1.0,
+        };
+        level.collides_with(&ground_check)
+    }
+}
@@ -1,16 +1,16 @@
-use ggez::glam::Vec2;
-use ggez::graphics::Rect;
+use std::rc::Rc;
 
-use crate::actor::{Actor, ActorType};
-use crate::animation_handler::Animation;
-use crate::collision::find_mtv;
+use ggez::graphics::{Color, Rect};
+
+use crate::actor::Actor;
+use crate::animation_handler::{AnimationDef, AnimationLibrary};
 use crate::constants::{
-    GROUND_TILE_HEIGHT, GROUND_TILE_WIDTH, PLAYER_BBOX_HEIGHT, PLAYER_BBOX_WIDTH,
-    PLAYER_TILE_HEIGHT, PLAYER_TILE_WIDTH,
+    ATTACK_HITBOX_HEIGHT, ATTACK_HITBOX_WIDTH, GROUND_TILE_HEIGHT, GROUND_TILE_WIDTH,
 };
+use crate::entity::{Behavior, Entity, EntityId, SpriteSource};
 use crate::input_handler::InputState;
-use crate::level_handler::LevelHandler;
-use crate::primitives::{Dimensions, Direction, Point2};
+use crate::physics::PhysicsBody;
+use crate::primitives::{Direction, Point2};
 
 #[derive(Debug)]
 pub enum PlayerState {
@@ -18,6 +18,7 @@ pub enum PlayerState {
     Walking,
     Running,
     Jumping,
+    Attacking,
 }
 
 impl PlayerState {
@@ -27,151 +28,270 @@ impl PlayerState {
             PlayerState::Walking => JUMP_VELOCITY * 1.16,
             PlayerState::Running => JUMP_VELOCITY * 1.4,
             PlayerState::Jumping => JUMP_VELOCITY,
+            PlayerState::Attacking => JUMP_VELOCITY,
         }
     }
 }
 
+/// Player state that is not part of the player's entity.
 #[derive(Debug)]
 pub struct Player {
-    pub actor: Actor,
-    pub animation: Animation,
+    /// The player's entity in the game's entity store
+    pub entity: EntityId,
     pub state: PlayerState,
-    pub velocity: Vec2,
-    pub grounded: bool,
+    pub health: u32,
+    /// Seconds left until the player can be damaged again
+    pub invulnerable: f32,
+    /// Set between the hit start and end events of the attack animation
+    attack_active: bool,
+    animations: PlayerAnimations,
+}
+
+/// Player character animations, from the animation library
+#[derive(Debug)]
+struct PlayerAnimations {
+    idle: Rc<AnimationDef>,
+    walk: Rc<AnimationDef>,
+    run: Rc<AnimationDef>,
+    jump: Rc<AnimationDef>,
+    attack: Rc<AnimationDef>,
 }
 
+impl PlayerAnimations {
+    fn new(library: &AnimationLibrary) -> PlayerAnimations {
+        let get = |action: &str| library.get(&format!("{}_{}", PLAYER_CHARACTER, action));
+        PlayerAnimations {
+            idle: get("idle"),
+            walk: get("walk"),
+            run: get("run"),
+            jump: get("jump"),
+            attack: get("attack"),
+        }
+    }
+}
+
+/// Prefix of the player's animation names in the animation library
+const PLAYER_CHARACTER: &str = "knight";
+
 // Speeds are pixels per second
 const WALKING_SPEED: f32 = 240.0;
 const RUNNING_SPEED: f32 = 360.0;
 const JUMP_VELOCITY: f32 = 500.0;
-const GRAVITY: f32 = 2000.0;
-const MAX_VELOCITY_Y: f32 = 1200.0;
+const KNOCKBACK_VELOCITY: f32 = 400.0;
+
+/// Attack hitbox position, relative to the player's bbox
+const ATTACK_HITBOX_Y: f32 = 16.0;
+/// Animation events that start and end the attack hitbox
+const HIT_START_EVENT: &str = "hit_start";
+const HIT_END_EVENT: &str = "hit_end";
+
+pub const MAX_HEALTH: u32 = 3;
+/// Position of the player at the start of the level
+pub const START_POSITION: Point2 = Point2::new(5.0 * GROUND_TILE_WIDTH, 5.0 * GROUND_TILE_HEIGHT);
+const INVULNERABILITY_TIME: f32 = 1.5;
 
 impl Player {
-    pub fn create() -> Player {
-        let x = 5.0 * GROUND_TILE_WIDTH;
-        let y = 5.0 * GROUND_TILE_HEIGHT;
-        let bbox = Dimensions::new(PLAYER_BBOX_WIDTH, PLAYER_BBOX_HEIGHT);
+    /// Creates the player's entity at the level start position.
+    pub fn create_entity(animations: &AnimationLibrary) -> Entity {
+        Entity {
+            body: Some(PhysicsBody::new()),
+            ..Entity::new(
+                Actor::create_knight(START_POSITION, Direction::Right),
+                SpriteSource::Animation(animations.create(&format!("{}_idle", PLAYER_CHARACTER))),
+                Behavior::Player,
+            )
+        }
+    }
+
+    pub fn create(entity: EntityId, animations: &AnimationLibrary) -> Player {
         Player {
-            actor: Actor {
-                tag: ActorType::Player,
-                pos: Point2::new(x, y),
-                facing: Direction::Right,
-                sprite_size: Dimensions::new(PLAYER_TILE_WIDTH, PLAYER_TILE_HEIGHT),
-                draw_offset: Point2::new((bbox.x - PLAYER_TILE_WIDTH) / 2.0, 0.0),
-                bbox: Rect {
-                    x,
-                    y,
-                    w: bbox.x,
-                    h: bbox.y,
-                },
-            },
-            animation: Animation::player_idle(),
+            entity,
             state: PlayerState::Standing,
-            velocity: Vec2::new(0.0, 0.0),
-            grounded: true,
+            health: MAX_HEALTH,
+            invulnerable: 0.0,
+            attack_active: false,
+            animations: PlayerAnimations::new(animations),
         }
     }
 
-    pub fn handle_input(&mut self, input: &InputState, seconds: f32, level: &LevelHandler) {
-        self.update_player_action(input);
-        self.calc_player_pos(seconds, level);
+    /// Updates the player's actions based on input. Movement is applied by the physics update.
+    pub fn handle_input(&mut self, entity: &mut Entity, input: &InputState, seconds: f32) {
+        self.invulnerable = (self.invulnerable - seconds).max(0.0);
+        entity.tint = match self.is_invulnerable() {
+            true => Color::new(1.0, 1.0, 1.0, 0.5),
+            false => Color::WHITE,
+        };
+        self.update_player_action(entity, input);
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable > 0.0
+    }
+
+    /// Damages the player, unless recently damaged.
+    /// Returns true if the player was damaged.
+    pub fn take_hit(&mut self, entity: &mut Entity) -> bool {
+        if self.is_invulnerable() || self.health == 0 {
+            return false;
+        }
+        self.health -= 1;
+        self.invulnerable = INVULNERABILITY_TIME;
+        let body = Self::body(entity);
+        body.velocity.y = KNOCKBACK_VELOCITY;
+        body.grounded = false;
+        true
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health == 0
+    }
+
+    /// Returns the area hit by the player's attack, if an attack is currently active.
+    pub fn attack_hitbox(&self, entity: &Entity) -> Option<Rect> {
+        if !matches!(self.state, PlayerState::Attacking) || !self.attack_active {
+            return None;
+        }
+        let bbox = &entity.actor.bbox;
+        let x = match entity.actor.facing {
+            Direction::Left => bbox.x - ATTACK_HITBOX_WIDTH,
+            Direction::Right => bbox.x + bbox.w,
+        };
+        Some(Rect {
+            x,
+            y: bbox.y + ATTACK_HITBOX_Y,
+            w: ATTACK_HITBOX_WIDTH,
+            h: ATTACK_HITBOX_HEIGHT,
+        })
     }
 
-    fn update_player_action(&mut self, input: &InputState) {
+    /// Reacts to an event from the player's current animation.
+    pub fn handle_animation_event(&mut self, event: &str) {
+        match event {
+            HIT_START_EVENT => self.attack_active = true,
+            HIT_END_EVENT => self.attack_active = false,
+            _ => {}
+        }
+    }
+
+    /// Scales walk and run cycle speed by how far the player moved during the physics update,
+    /// so that the feet match the movement: walking into a wall stops the cycle.
+    pub fn update_animation_speed(&self, entity: &mut Entity, moved_x: f32, seconds: f32) {
+        let Some(speed) = playback_speed(&self.state, moved_x, seconds) else {
+            return;
+        };
+        if let Some(animation) = entity.animation_mut() {
+            animation.set_speed(speed);
+        }
+    }
+
+    fn body(entity: &mut Entity) -> &mut PhysicsBody {
+        entity
+            .body
+            .as_mut()
+            .expect("Player entity must have a physics body")
+    }
+
+    fn update_player_action(&mut self, entity: &mut Entity, input: &InputState) {
+        if matches!(self.state, PlayerState::Attacking) {
+            if !entity.animation().is_some_and(|a| a.is_finished()) {
+                return;
+            }
+            self.state = PlayerState::Standing;
+            self.attack_active = false;
+            entity.play_animation(self.animations.idle.clone());
+        }
+        let grounded = Self::body(entity).grounded;
+        if input.attack_pressed && grounded {
+            return self.attack(entity);
+        }
         if input.jump {
-            return self.jump();
+            return self.jump(entity, grounded);
         }
         if let Some(dir) = input.move_x() {
-            self.actor.facing = dir;
+            entity.actor.facing = dir;
             if input.running {
-                return self.run(dir);
+                return self.run(entity, dir, grounded);
             } else {
-                return self.walk(dir);
+                return self.walk(entity, dir, grounded);
             }
         }
-        self.idle()
+        self.idle(entity, grounded)
     }
 
-    fn idle(&mut self) {
-        self.velocity.x = 0.0;
-        if !matches!(self.state, PlayerState::Standing) && self.grounded {
+    fn idle(&mut self, entity: &mut Entity, grounded: bool) {
+        Self::body(entity).velocity.x = 0.0;
+        if !matches!(self.state, PlayerState::Standing) && grounded {
             self.state = PlayerState::Standing;
-            self.animation = Animation::player_idle();
+            entity.play_animation(self.animations.idle.clone());
         }
     }
 
-    fn walk(&mut self, direction: Direction) {
-        self.velocity.x = direction.mult() * WALKING_SPEED;
-        self.actor.facing = direction;
-        if !matches!(self.state, PlayerState::Walking) && self.grounded {
+    fn walk(&mut self, entity: &mut Entity, direction: Direction, grounded: bool) {
+        Self::body(entity).velocity.x = direction.mult() * WALKING_SPEED;
+        entity.actor.facing = direction;
+        if !matches!(self.state, PlayerState::Walking) && grounded {
             self.state = PlayerState::Walking;
-            self.animation = Animation::player_walking();
+            entity.play_animation(self.animations.walk.clone());
         }
     }
 
-    fn run(&mut self, direction: Direction) {
-        self.actor.facing = direction;
-        self.velocity.x = direction.mult() * RUNNING_SPEED;
-        if !matches!(self.state, PlayerState::Running) && self.grounded {
+    fn run(&mut self, entity: &mut Entity, direction: Direction, grounded: bool) {
+        entity.actor.facing = direction;
+        Self::body(entity).velocity.x = direction.mult() * RUNNING_SPEED;
+        if !matches!(self.state, PlayerState::Running) && grounded {
             self.state = PlayerState::Running;
-            self.animation = Animation::player_running();
+            entity.play_animation(self.animations.run.clone());
         }
     }
 
-    fn jump(&mut self) {
-        if !matches!(self.state, PlayerState::Jumping) && self.grounded {
-            self.velocity.y += self.state.jump_speed();
-            self.state = PlayerState::Jumping;
-            self.animation = Animation::player_jumping();
-        }
+    fn attack(&mut self, entity: &mut Entity) {
+        Self::body(entity).velocity.x = 0.0;
+        self.state = PlayerState::Attacking;
+        self.attack_active = false;
+        entity.play_animation(self.animations.attack.clone());
     }
 
-    fn calc_player_pos(&mut self, seconds: f32, level: &LevelHandler) {
-        self.velocity.y = self.velocity.y.max(-MAX_VELOCITY_Y);
-        // Move player along x
-        self.move_by(self.velocity.x * seconds, 0.0);
-        // Check for collision on x-axis
-        self.check_collision(true, level);
-        // Move player along y
-        self.move_by(0.0, self.velocity.y * seconds);
-        // Check for collision on x-axis
-        self.check_collision(false, level);
-        // Update gravity (takes effect on next round, will be reset if player is grounded)
-        self.velocity.y -= GRAVITY * seconds;
-
-        self.grounded = self.is_grounded(level);
-        if self.grounded {
-            self.velocity.y = 0.0;
+    fn jump(&mut self, entity: &mut Entity, grounded: bool) {
+        if !matches!(self.state, PlayerState::Jumping) && grounded {
+            Self::body(entity).velocity.y += self.state.jump_speed();
+            self.state = PlayerState::Jumping;
+            entity.play_animation(self.animations.jump.clone());
         }
     }
+}
 
-    fn move_by(&mut self, x: f32, y: f32) {
-        self.actor.pos.x += x;
-        self.actor.pos.y += y;
-        self.actor.update_bbox();
-    }
-
-    fn check_collision(&mut self, along_x: bool, level: &LevelHandler) {
-        let collisions = level.get_collisions(&self.actor.bbox);
-        for c in collisions {
-            self.resolve_collision(c, along_x);
-        }
+/// Returns the walk or run cycle speed multiplier for a horizontal movement,
+/// or `None` if the state's animation is not scaled.
+fn playback_speed(state: &PlayerState, moved_x: f32, seconds: f32) -> Option<f32> {
+    let reference_speed = match state {
+        PlayerState::Walking => WALKING_SPEED,
+        PlayerState::Running => RUNNING_SPEED,
+        _ => return None,
+    };
+    if seconds <= 0.0 {
+        return None;
     }
+    Some(moved_x.abs() / seconds / reference_speed)
+}
 
-    fn resolve_collision(&mut self, actor: &Actor, along_x: bool) {
-        if let Some(offs) = find_mtv(&self.actor.bbox, &actor.bbox, self.velocity, along_x) {
-            self.move_by(offs.x, offs.y)
-        }
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn is_grounded(&self, level: &LevelHandler) -> bool {
-        let ground_check = Rect {
-            x: self.actor.bbox.x,
-            y: self.actor.bbox.y - 1.0,
-            w: self.actor.bbox.w,
-            h: 1.0,
-        };
-        level.collides_with(&ground_check)
+    #[test]
+    fn playback_speed_follows_actual_movement() {
+        let walking = PlayerState::Walking;
+        assert_eq!(
+            playback_speed(&walking, WALKING_SPEED * 0.1, 0.1),
+            Some(1.0)
+        );
+        assert_eq!(
+            playback_speed(&walking, 0.0, 0.1),
+            Some(0.0),
+            "blocked by a wall"
+        );
+        let slowed = playback_speed(&PlayerState::Running, -RUNNING_SPEED * 0.05, 0.1);
+        assert!(slowed.is_some_and(|s| s < 1.0));
+        assert_eq!(playback_speed(&PlayerState::Jumping, 10.0, 0.1), None);
     }
 }
@@ -1,7 +1,7 @@
 use ggez::glam::Vec2;
 use ggez::graphics::Rect;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Direction {
     Left,
     Right,
@@ -14,6 +14,13 @@ impl Direction {
             Direction::Right => 1.0,
         }
     }
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
 }
 
 pub type Point2 = Vec2;
@@ -0,0 +1,144 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use ggez::{Context, GameResult};
+
+use crate::persist::{self, DataFile};
+
+/// Save file name, in the user config directory.
+const SAVE_FILE: &str = "save.txt";
+/// Save file format version.
+const SAVE_VERSION: u32 = 1;
+
+/// Campaign progress that is kept between runs.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SaveGame {
+    /// Names of the levels that have been completed
+    pub completed_levels: BTreeSet<String>,
+    /// Names of the abilities the player has unlocked
+    pub abilities: BTreeSet<String>,
+    /// Fastest completion time of each level, in seconds
+    pub best_times: BTreeMap<String, f32>,
+    /// Most items collected in a completed run of each level
+    pub collected: BTreeMap<String, usize>,
+}
+
+impl SaveGame {
+    pub fn path(ctx: &Context) -> PathBuf {
+        ctx.fs.user_config_dir().join(SAVE_FILE)
+    }
+
+    /// Loads the save file. A missing file starts a new game; a file that cannot be read
+    /// is moved aside, so that the progress in it is not overwritten, and a new game is started.
+    pub fn load(path: &Path) -> SaveGame {
+        let result = match persist::read(path) {
+            Ok(Some(text)) => Self::parse(&text),
+            Ok(None) => return SaveGame::default(),
+            Err(e) => Err(e.to_string()),
+        };
+        result.unwrap_or_else(|e| {
+            persist::quarantine(path, &e);
+            SaveGame::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> GameResult {
+        persist::write_atomic(path, &DataFile::to_text(SAVE_VERSION, &self.entries()))
+    }
+
+    /// Records a completed run of a level. Returns true if it was the fastest run so far.
+    pub fn complete_level(&mut self, level: &str, time: f32, collected: usize) -> bool {
+        self.completed_levels.insert(level.to_string());
+        let most = self.collected.entry(level.to_string()).or_default();
+        *most = (*most).max(collected);
+        let best = self.best_times.get(level).is_none_or(|best| time < *best);
+        if best {
+            self.best_times.insert(level.to_string(), time);
+        }
+        best
+    }
+
+    /// Parses a save file. Unlike settings, any invalid line fails the whole file,
+    /// as partially read progress could silently lose the rest.
+    fn parse(text: &str) -> Result<SaveGame, String> {
+        let file = DataFile::parse(text)?;
+        match file.version {
+            Some(SAVE_VERSION) => {}
+            Some(version) => return Err(format!("unsupported save version {}", version)),
+            None => return Err(String::from("missing save version")),
+        }
+        let mut save = SaveGame::default();
+        for (key, value) in &file.entries {
+            let invalid = || format!("invalid save entry: {} {}", key, value);
+            match key.as_str() {
+                "completed" => {
+                    save.completed_levels.insert(value.clone());
+                }
+                "ability" => {
+                    save.abilities.insert(value.clone());
+                }
+                // Level names may contain spaces, so the number is the last field
+                "best_time" => {
+                    let (level, time) = value.rsplit_once(' ').ok_or_else(invalid)?;
+                    let time: f32 = time.parse().map_err(|_| invalid())?;
+                    save.best_times.insert(level.to_string(), time);
+                }
+                "collected" => {
+                    let (level, count) = value.rsplit_once(' ').ok_or_else(invalid)?;
+                    let count = count.parse().map_err(|_| invalid())?;
+                    save.collected.insert(level.to_string(), count);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(save)
+    }
+
+    fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        for level in &self.completed_levels {
+            entries.push((String::from("completed"), level.clone()));
+        }
+        for ability in &self.abilities {
+            entries.push((String::from("ability"), ability.clone()));
+        }
+        for (level, time) in &self.best_times {
+            entries.push((String::from("best_time"), format!("{} {}", level, time)));
+        }
+        for (level, count) in &self.collected {
+            entries.push((String::from("collected"), format!("{} {}", level, count)));
+        }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_round_trip() {
+        let mut save = SaveGame::default();
+        save.complete_level("level one", 42.5, 10);
+        save.abilities.insert(String::from("double_jump"));
+        let text = DataFile::to_text(SAVE_VERSION, &save.entries());
+        assert_eq!(SaveGame::parse(&text), Ok(save));
+    }
+
+    #[test]
+    fn keeps_best_results() {
+        let mut save = SaveGame::default();
+        assert!(save.complete_level("level", 50.0, 8));
+        assert!(!save.complete_level("level", 60.0, 12));
+        assert!(save.complete_level("level", 40.0, 5));
+        assert_eq!(save.best_times["level"], 40.0);
+        assert_eq!(save.collected["level"], 12);
+    }
+
+    #[test]
+    fn rejects_corrupt_saves() {
+        assert!(SaveGame::parse("completed level\n").is_err());
+        assert!(SaveGame::parse("version 1\nbest_time level fast\n").is_err());
+        assert!(SaveGame::parse("version 1\ngarbage\n").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use ggez::graphics::Canvas;
+use ggez::input::keyboard::KeyCode;
+use ggez::Context;
+
+use crate::game::SpriteGame;
+
+/// Change to the scene stack requested by the top scene.
+pub enum SceneAction {
+    None,
+    /// Puts a scene on top of the current one
+    Push(Box<dyn Scene>),
+    /// Removes the current scene, returning to the one below it
+    Pop,
+    /// Removes all scenes and starts over from the given scene
+    Reset(Box<dyn Scene>),
+    Quit,
+}
+
+/// A screen of the game, such as the title screen, gameplay or a menu.
+/// Only the top scene of the stack is updated and receives key presses.
+pub trait Scene {
+    /// Called once per fixed update step.
+    fn update(&mut self, _game: &mut SpriteGame, _ctx: &mut Context, _seconds: f32) -> SceneAction {
+        SceneAction::None
+    }
+
+    fn draw(&mut self, game: &mut SpriteGame, canvas: &mut Canvas);
+
+    /// Called once per key press; key repeats are ignored.
+    fn key_pressed(
+        &mut self,
+        _game: &mut SpriteGame,
+        _ctx: &mut Context,
+        _key: KeyCode,
+    ) -> SceneAction {
+        SceneAction::None
+    }
+
+    /// Overlays are drawn on top of the scene below them, instead of replacing it.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// The game and the stack of scenes that are showing it.
+pub struct SceneStack {
+    pub game: SpriteGame,
+    scenes: Vec<Box<dyn Scene>>,
+}
+
+impl SceneStack {
+    pub fn new(game: SpriteGame, first: Box<dyn Scene>) -> SceneStack {
+        SceneStack {
+            game,
+            scenes: vec![first],
+        }
+    }
+
+    /// Returns false when the last scene has been closed and the game should quit.
+    pub fn is_running(&self) -> bool {
+        !self.scenes.is_empty()
+    }
+
+    pub fn update_scene(&mut self, ctx: &mut Context, seconds: f32) {
+        if let Some(scene) = self.scenes.last_mut() {
+            let action = scene.update(&mut self.game, ctx, seconds);
+            self.apply(action);
+        }
+    }
+
+    pub fn key_pressed(&mut self, ctx: &mut Context, key: KeyCode) {
+        if let Some(scene) = self.scenes.last_mut() {
+            let action = scene.key_pressed(&mut self.game, ctx, key);
+            self.apply(action);
+        }
+    }
+
+    /// Draws the top scene, and the scenes below it that it is an overlay for.
+    pub fn draw_scenes(&mut self, canvas: &mut Canvas) {
+        let first = self
+            .scenes
+            .iter()
+            .rposition(|s| !s.is_overlay())
+            .unwrap_or(0);
+        for scene in &mut self.scenes[first..] {
+            scene.draw(&mut self.game, canvas);
+        }
+    }
+
+    fn apply(&mut self, action: SceneAction) {
+        match action {
+            SceneAction::None => {}
+            SceneAction::Push(scene) => self.scenes.push(scene),
+            SceneAction::Pop => {
+                self.scenes.pop();
+            }
+            SceneAction::Reset(scene) => {
+                self.scenes.clear();
+                self.scenes.push(scene);
+            }
+            SceneAction::Quit => self.scenes.clear(),
+        }
+    }
+}
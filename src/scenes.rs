@@ -0,0 +1,346 @@
+use ggez::graphics::{Canvas, Color};
+use ggez::input::keyboard::KeyCode;
+use ggez::Context;
+
+use crate::editor::EditorScene;
+use crate::game::SpriteGame;
+use crate::hud::format_time;
+use crate::menu::{draw_centered, Menu, MenuAction};
+use crate::scene::{Scene, SceneAction};
+
+/// Music track played on the title screen
+const TITLE_MUSIC: &str = "title";
+/// Volume change per key press in the settings menu
+const VOLUME_STEP: f32 = 0.1;
+
+pub struct TitleScene {
+    menu: Menu,
+}
+
+impl TitleScene {
+    pub fn new() -> TitleScene {
+        TitleScene {
+            menu: Menu::new("Sprite Knight", 3),
+        }
+    }
+}
+
+impl Scene for TitleScene {
+    fn update(&mut self, game: &mut SpriteGame, _ctx: &mut Context, _seconds: f32) -> SceneAction {
+        game.audio.play_music(TITLE_MUSIC);
+        SceneAction::None
+    }
+
+    fn draw(&mut self, game: &mut SpriteGame, canvas: &mut Canvas) {
+        game.draw_frame(canvas);
+        let items = ["Start", "Settings", "Quit"].map(String::from);
+        self.menu.draw(&game.gfx, &items, canvas);
+    }
+
+    fn key_pressed(
+        &mut self,
+        game: &mut SpriteGame,
+        _ctx: &mut Context,
+        key: KeyCode,
+    ) -> SceneAction {
+        match self.menu.handle_key(key) {
+            MenuAction::Select(0) => {
+                game.restart_level();
+                SceneAction::Reset(Box::new(GameplayScene::new()))
+            }
+            MenuAction::Select(1) => SceneAction::Push(Box::new(SettingsScene::new())),
+            MenuAction::Select(_) | MenuAction::Back => SceneAction::Quit,
+            _ => SceneAction::None,
+        }
+    }
+}
+
+/// Runs the game world.
+pub struct GameplayScene {
+    /// Set when playing a level from the level editor; such runs are not saved
+    test_play: bool,
+}
+
+impl GameplayScene {
+    pub fn new() -> GameplayScene {
+        GameplayScene { test_play: false }
+    }
+
+    /// Plays the level from the level editor, and returns to the editor with F2.
+    pub fn test_play() -> GameplayScene {
+        GameplayScene { test_play: true }
+    }
+}
+
+impl Scene for GameplayScene {
+    fn update(&mut self, game: &mut SpriteGame, ctx: &mut Context, seconds: f32) -> SceneAction {
+        // Each level has a music track with the same name as the level
+        let music = game.level.name.clone();
+        game.audio.play_music(&music);
+
+        game.update_entities(seconds);
+        game.collect_items();
+        game.check_attack_hits();
+        game.check_enemy_contact();
+
+        if game.player.is_dead() {
+            SceneAction::Push(Box::new(GameOverScene::new()))
+        } else if game.score.is_complete() {
+            let new_best = !self.test_play && game.complete_level(ctx);
+            SceneAction::Push(Box::new(LevelCompleteScene::new(new_best)))
+        } else {
+            SceneAction::None
+        }
+    }
+
+    fn draw(&mut self, game: &mut SpriteGame, canvas: &mut Canvas) {
+        game.draw_frame(canvas);
+    }
+
+    fn key_pressed(
+        &mut self,
+        game: &mut SpriteGame,
+        _ctx: &mut Context,
+        key: KeyCode,
+    ) -> SceneAction {
+        match key {
+            KeyCode::Escape => SceneAction::Push(Box::new(PauseScene::new())),
+            KeyCode::R => {
+                game.restart_level();
+                SceneAction::None
+            }
+            KeyCode::F2 if self.test_play => {
+                game.restart_level();
+                SceneAction::Pop
+            }
+            KeyCode::F2 => SceneAction::Push(Box::new(EditorScene::new())),
+            // Set here rather than in the input state, so that attack keys
+            // pressed in menus do not attack when gameplay resumes
+            _ if game.input.bindings.attack.contains(&key) => {
+                game.input.attack_pressed = true;
+                SceneAction::None
+            }
+            _ => SceneAction::None,
+        }
+    }
+}
+
+pub struct PauseScene {
+    menu: Menu,
+}
+
+impl PauseScene {
+    pub fn new() -> PauseScene {
+        PauseScene {
+            menu: Menu::new("Paused", 4),
+        }
+    }
+}
+
+impl Scene for PauseScene {
+    fn draw(&mut self, game: &mut SpriteGame, canvas: &mut Canvas) {
+        let items = ["Resume", "Restart level", "Settings", "Quit to title"].map(String::from);
+        self.menu.draw(&game.gfx, &items, canvas);
+    }
+
+    fn key_pressed(
+        &mut self,
+        game: &mut SpriteGame,
+        _ctx: &mut Context,
+        key: KeyCode,
+    ) -> SceneAction {
+        match self.menu.handle_key(key) {
+            MenuAction::Select(0) | MenuAction::Back => SceneAction::Pop,
+            MenuAction::Select(1) => {
+                game.restart_level();
+                SceneAction::Pop
+            }
+            MenuAction::Select(2) => SceneAction::Push(Box::new(SettingsScene::new())),
+            MenuAction::Select(_) => SceneAction::Reset(Box::new(TitleScene::new())),
+            _ => SceneAction::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+pub struct SettingsScene {
+    menu: Menu,
+}
+
+impl SettingsScene {
+    pub fn new() -> SettingsScene {
+        SettingsScene {
+            menu: Menu::new("Settings", 6),
+        }
+    }
+
+    fn items(game: &SpriteGame) -> Vec<String> {
+        let settings = &game.settings;
+        let percent = |v: f32| (v * 100.0).round();
+        vec![
+            format!(
+                "Fullscreen: {}",
+                if settings.fullscreen { "on" } else { "off" }
+            ),
+            format!("Scaling: {}", settings.scale_mode.name()),
+            format!("Master volume: {}%", percent(settings.volumes.master)),
+            format!("Music volume: {}%", percent(settings.volumes.music)),
+            format!("Sound volume: {}%", percent(settings.volumes.sfx)),
+            String::from("Back"),
+        ]
+    }
+
+    /// Changes the setting of a menu item; `step` is -1 or 1.
+    fn change(game: &mut SpriteGame, ctx: &mut Context, item: usize, step: i32) {
+        let mut volumes = game.settings.volumes;
+        let volume = match item {
+            0 => {
+                if let Err(e) = game.toggle_fullscreen(ctx) {
+                    println!("Could not change window mode: {}", e);
+                }
+                return;
+            }
+            1 => return game.cycle_scale_mode(ctx),
+            2 => &mut volumes.master,
+            3 => &mut volumes.music,
+            4 => &mut volumes.sfx,
+            _ => return,
+        };
+        // Rounded to whole steps, so that repeated changes do not accumulate errors
+        let steps = (*volume / VOLUME_STEP).round() + step as f32;
+        *volume = (steps * VOLUME_STEP).clamp(0.0, 1.0);
+        game.set_volumes(ctx, volumes);
+    }
+}
+
+impl Scene for SettingsScene {
+    fn draw(&mut self, game: &mut SpriteGame, canvas: &mut Canvas) {
+        self.menu.draw(&game.gfx, &Self::items(game), canvas);
+    }
+
+    fn key_pressed(
+        &mut self,
+        game: &mut SpriteGame,
+        ctx: &mut Context,
+        key: KeyCode,
+    ) -> SceneAction {
+        match self.menu.handle_key(key) {
+            MenuAction::Select(5) | MenuAction::Back => SceneAction::Pop,
+            MenuAction::Select(item) => {
+                Self::change(game, ctx, item, 1);
+                SceneAction::None
+            }
+            MenuAction::Adjust(item, step) => {
+                Self::change(game, ctx, item, step);
+                SceneAction::None
+            }
+            MenuAction::None => SceneAction::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+/// Shown when the player has run out of health.
+pub struct GameOverScene {
+    menu: Menu,
+}
+
+impl GameOverScene {
+    pub fn new() -> GameOverScene {
+        GameOverScene {
+            menu: Menu::new("Game Over", 2),
+        }
+    }
+}
+
+impl Scene for GameOverScene {
+    fn draw(&mut self, game: &mut SpriteGame, canvas: &mut Canvas) {
+        let items = ["Try again", "Quit to title"].map(String::from);
+        self.menu.draw(&game.gfx, &items, canvas);
+    }
+
+    fn key_pressed(
+        &mut self,
+        game: &mut SpriteGame,
+        _ctx: &mut Context,
+        key: KeyCode,
+    ) -> SceneAction {
+        match self.menu.handle_key(key) {
+            MenuAction::Select(0) => {
+                game.restart_level();
+                SceneAction::Pop
+            }
+            MenuAction::Select(_) | MenuAction::Back => {
+                SceneAction::Reset(Box::new(TitleScene::new()))
+            }
+            _ => SceneAction::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+/// Shown when the player has picked up all collectibles of the level.
+pub struct LevelCompleteScene {
+    menu: Menu,
+    /// Set if the level was completed faster than before
+    new_best: bool,
+}
+
+impl LevelCompleteScene {
+    pub fn new(new_best: bool) -> LevelCompleteScene {
+        LevelCompleteScene {
+            menu: Menu::new("Level Complete", 2),
+            new_best,
+        }
+    }
+}
+
+impl Scene for LevelCompleteScene {
+    fn draw(&mut self, game: &mut SpriteGame, canvas: &mut Canvas) {
+        let items = ["Play again", "Quit to title"].map(String::from);
+        self.menu.draw(&game.gfx, &items, canvas);
+        let score = format!(
+            "Score: {}   Time: {}",
+            game.score.score,
+            format_time(game.level_time)
+        );
+        draw_centered(&score, 30.0, 540.0, Color::WHITE, canvas);
+        let best = match game.save.best_times.get(&game.level.name) {
+            _ if self.new_best => String::from("New best time!"),
+            Some(best) => format!("Best: {}", format_time(*best)),
+            None => String::new(),
+        };
+        draw_centered(&best, 30.0, 580.0, Color::WHITE, canvas);
+    }
+
+    fn key_pressed(
+        &mut self,
+        game: &mut SpriteGame,
+        _ctx: &mut Context,
+        key: KeyCode,
+    ) -> SceneAction {
+        match self.menu.handle_key(key) {
+            MenuAction::Select(0) => {
+                game.restart_level();
+                SceneAction::Pop
+            }
+            MenuAction::Select(_) | MenuAction::Back => {
+                SceneAction::Reset(Box::new(TitleScene::new()))
+            }
+            _ => SceneAction::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
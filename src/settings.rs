@@ -0,0 +1,191 @@
+use std::path::{Path, PathBuf};
+
+use ggez::conf::FullscreenType;
+use ggez::{Context, GameResult};
+
+use crate::audio::Volumes;
+use crate::input_handler::KeyBindings;
+use crate::persist::{self, DataFile};
+use crate::viewport::ScaleMode;
+
+/// Settings file name, in the user config directory.
+const SETTINGS_FILE: &str = "settings.txt";
+/// Settings file format version. Files without a version line are from before
+/// key bindings were added, and are otherwise the same.
+const SETTINGS_VERSION: u32 = 2;
+
+/// User settings that are remembered between runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// Borderless fullscreen, or a window
+    pub fullscreen: bool,
+    pub scale_mode: ScaleMode,
+    pub volumes: Volumes,
+    pub bindings: KeyBindings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            fullscreen: false,
+            scale_mode: ScaleMode::Integer,
+            volumes: Volumes::default(),
+            bindings: KeyBindings::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn path(ctx: &Context) -> PathBuf {
+        ctx.fs.user_config_dir().join(SETTINGS_FILE)
+    }
+
+    /// Loads settings from a file. Uses defaults for a missing file and for invalid values.
+    /// A file that cannot be read at all is moved aside, and defaults are used instead.
+    pub fn load(path: &Path) -> Settings {
+        let result = match persist::read(path) {
+            Ok(Some(text)) => Self::parse(&text),
+            Ok(None) => return Settings::default(),
+            Err(e) => Err(e.to_string()),
+        };
+        result.unwrap_or_else(|e| {
+            persist::quarantine(path, &e);
+            Settings::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> GameResult {
+        persist::write_atomic(path, &DataFile::to_text(SETTINGS_VERSION, &self.entries()))
+    }
+
+    /// Parses a settings file. Invalid values are ignored, but a file
+    /// from a newer version of the game is rejected.
+    fn parse(text: &str) -> Result<Settings, String> {
+        let file = DataFile::parse(text)?;
+        match file.version {
+            None | Some(SETTINGS_VERSION) => {}
+            Some(version) => return Err(format!("unsupported settings version {}", version)),
+        }
+        let mut settings = Settings::default();
+        for (key, value) in &file.entries {
+            if !settings.apply(key, value) {
+                println!("Ignoring invalid setting: {} {}", key, value);
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Sets a value from the settings file. Returns false if the key or value is invalid.
+    fn apply(&mut self, key: &str, value: &str) -> bool {
+        if let Some(action) = key.strip_prefix("key_") {
+            let keys = KeyBindings::parse_keys(value);
+            return match (self.bindings.keys_mut(action), keys) {
+                (Some(bound), Some(keys)) => {
+                    *bound = keys;
+                    true
+                }
+                _ => false,
+            };
+        }
+        match key {
+            "fullscreen" => value.parse().map(|v| self.fullscreen = v).is_ok(),
+            "scale_mode" => ScaleMode::parse(value)
+                .map(|v| self.scale_mode = v)
+                .is_some(),
+            "master_volume" => Self::parse_volume(value)
+                .map(|v| self.volumes.master = v)
+                .is_some(),
+            "music_volume" => Self::parse_volume(value)
+                .map(|v| self.volumes.music = v)
+                .is_some(),
+            "sfx_volume" => Self::parse_volume(value)
+                .map(|v| self.volumes.sfx = v)
+                .is_some(),
+            _ => false,
+        }
+    }
+
+    /// Parses a volume level from 0 to 1.
+    fn parse_volume(value: &str) -> Option<f32> {
+        value.parse().ok().filter(|v| (0.0..=1.0).contains(v))
+    }
+
+    fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = vec![
+            (String::from("fullscreen"), self.fullscreen.to_string()),
+            (
+                String::from("scale_mode"),
+                self.scale_mode.name().to_string(),
+            ),
+            (
+                String::from("master_volume"),
+                self.volumes.master.to_string(),
+            ),
+            (String::from("music_volume"), self.volumes.music.to_string()),
+            (String::from("sfx_volume"), self.volumes.sfx.to_string()),
+        ];
+        for action in KeyBindings::ACTIONS {
+            if let Some(keys) = self.bindings.keys(action) {
+                entries.push((format!("key_{}", action), KeyBindings::format_keys(keys)));
+            }
+        }
+        entries
+    }
+
+    pub fn fullscreen_type(&self) -> FullscreenType {
+        match self.fullscreen {
+            true => FullscreenType::Desktop,
+            false => FullscreenType::Windowed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ggez::input::keyboard::KeyCode;
+
+    use super::*;
+
+    #[test]
+    fn settings_round_trip() {
+        let mut settings = Settings {
+            fullscreen: true,
+            scale_mode: ScaleMode::Stretch,
+            volumes: Volumes {
+                master: 0.5,
+                music: 0.25,
+                sfx: 1.0,
+            },
+            ..Settings::default()
+        };
+        settings.bindings.jump = vec![KeyCode::Z, KeyCode::Up];
+        let text = DataFile::to_text(SETTINGS_VERSION, &settings.entries());
+        assert_eq!(Settings::parse(&text), Ok(settings));
+    }
+
+    #[test]
+    fn invalid_values_use_defaults() {
+        let settings = Settings::parse(
+            "fullscreen maybe\nscale_mode letterbox\nmusic_volume 3\nsfx_volume 0.5\nkey_jump Nope\n",
+        )
+        .unwrap();
+        assert!(!settings.fullscreen);
+        assert_eq!(settings.scale_mode, ScaleMode::Letterbox);
+        assert_eq!(settings.volumes.music, Volumes::default().music);
+        assert_eq!(settings.volumes.sfx, 0.5);
+        assert_eq!(settings.bindings, KeyBindings::default());
+    }
+
+    #[test]
+    fn migrates_unversioned_settings() {
+        let settings = Settings::parse("fullscreen true\nscale_mode stretch\n").unwrap();
+        assert!(settings.fullscreen);
+        assert_eq!(settings.scale_mode, ScaleMode::Stretch);
+        assert_eq!(settings.bindings, KeyBindings::default());
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        assert!(Settings::parse("version 99\nfullscreen true\n").is_err());
+    }
+}
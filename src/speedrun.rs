@@ -0,0 +1,304 @@
+use std::path::{Path, PathBuf};
+
+use ggez::{Context, GameResult};
+
+use crate::entity::Entity;
+use crate::persist::{self, DataFile};
+use crate::primitives::{Direction, Point2};
+
+/// Directory for the recorded best runs, in the user config directory.
+const GHOST_DIR: &str = "ghosts";
+/// Ghost file format version. Version 1 files stored splits in pickup order, without
+/// the collectible they belong to; their splits are dropped when loading.
+const GHOST_VERSION: u32 = 2;
+
+/// Identifies a collectible by its spawn position, as (x, y) tile indices in the level.
+pub type SplitKey = (usize, usize);
+
+/// Player position and sprite during one fixed update step of a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GhostFrame {
+    pub pos: Point2,
+    pub facing: Direction,
+    /// Name of the animation that was playing
+    pub animation: String,
+    /// Index of the shown frame in the animation's sprite sheet
+    pub frame: usize,
+}
+
+impl GhostFrame {
+    /// Captures the current position and animation frame of an animated entity.
+    pub fn capture(entity: &Entity) -> Option<GhostFrame> {
+        let animation = entity.animation()?;
+        Some(GhostFrame {
+            pos: entity.actor.pos,
+            facing: entity.actor.facing,
+            animation: animation.def.name.clone(),
+            frame: animation.get_sheet_frame(),
+        })
+    }
+
+    fn parse(value: &str) -> Option<GhostFrame> {
+        let fields: Vec<&str> = value.split_whitespace().collect();
+        let [x, y, facing, animation, frame] = fields[..] else {
+            return None;
+        };
+        let facing = match facing {
+            "left" => Direction::Left,
+            "right" => Direction::Right,
+            _ => return None,
+        };
+        Some(GhostFrame {
+            pos: Point2::new(x.parse().ok()?, y.parse().ok()?),
+            facing,
+            animation: animation.to_string(),
+            frame: frame.parse().ok()?,
+        })
+    }
+
+    fn to_text(&self) -> String {
+        let facing = match self.facing {
+            Direction::Left => "left",
+            Direction::Right => "right",
+        };
+        format!(
+            "{} {} {} {} {}",
+            self.pos.x, self.pos.y, facing, self.animation, self.frame
+        )
+    }
+}
+
+/// A timed run through a level, with one ghost frame per fixed update step.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Run {
+    /// Time of the last recorded step, in seconds
+    pub time: f32,
+    /// Times at which each collectible was picked up, in pickup order
+    pub splits: Vec<(SplitKey, f32)>,
+    pub frames: Vec<GhostFrame>,
+}
+
+impl Run {
+    pub fn path(ctx: &Context, level: &str) -> PathBuf {
+        ctx.fs
+            .user_config_dir()
+            .join(GHOST_DIR)
+            .join(format!("{}.txt", level))
+    }
+
+    /// Loads a recorded run. Returns `None` if the level has no recorded run;
+    /// a file that cannot be read is moved aside.
+    pub fn load(path: &Path) -> Option<Run> {
+        let result = match persist::read(path) {
+            Ok(Some(text)) => Self::parse(&text),
+            Ok(None) => return None,
+            Err(e) => Err(e.to_string()),
+        };
+        result.map_err(|e| persist::quarantine(path, &e)).ok()
+    }
+
+    pub fn save(&self, path: &Path) -> GameResult {
+        persist::write_atomic(path, &DataFile::to_text(GHOST_VERSION, &self.entries()))
+    }
+
+    fn parse(text: &str) -> Result<Run, String> {
+        let file = DataFile::parse(text)?;
+        if !matches!(file.version, Some(1 | GHOST_VERSION)) {
+            return Err(String::from("unsupported ghost version"));
+        }
+        let mut run = Run::default();
+        for (key, value) in &file.entries {
+            let invalid = || format!("invalid ghost entry: {} {}", key, value);
+            match key.as_str() {
+                "time" => run.time = value.parse().map_err(|_| invalid())?,
+                "split" if file.version == Some(1) => {}
+                "split" => run.splits.push(parse_split(value).ok_or_else(invalid)?),
+                "frame" => run
+                    .frames
+                    .push(GhostFrame::parse(value).ok_or_else(invalid)?),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(run)
+    }
+
+    fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = vec![(String::from("time"), self.time.to_string())];
+        for ((x, y), time) in &self.splits {
+            entries.push((String::from("split"), format!("{} {} {}", x, y, time)));
+        }
+        for frame in &self.frames {
+            entries.push((String::from("frame"), frame.to_text()));
+        }
+        entries
+    }
+}
+
+/// Parses a split entry (`x y time`).
+fn parse_split(value: &str) -> Option<(SplitKey, f32)> {
+    let fields: Vec<&str> = value.split_whitespace().collect();
+    let [x, y, time] = fields[..] else {
+        return None;
+    };
+    Some(((x.parse().ok()?, y.parse().ok()?), time.parse().ok()?))
+}
+
+/// A split time of the current run.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Split {
+    /// Number of the split, starting from 1
+    pub number: usize,
+    pub time: f32,
+    /// Difference to the time the best run picked up the same collectible; negative when ahead
+    pub delta: Option<f32>,
+}
+
+/// Records the current run of a level and replays the best one as a ghost.
+/// Runs are recorded per fixed update step, so a replay stays in sync with the game.
+#[derive(Debug, Default)]
+pub struct Speedrun {
+    current: Run,
+    best: Option<Run>,
+}
+
+impl Speedrun {
+    pub fn new(best: Option<Run>) -> Speedrun {
+        Speedrun {
+            current: Run::default(),
+            best,
+        }
+    }
+
+    /// Discards the current run, for example when the level is restarted.
+    pub fn restart(&mut self) {
+        self.current = Run::default();
+    }
+
+    /// Records one update step of the current run.
+    pub fn record(&mut self, time: f32, frame: GhostFrame) {
+        self.current.time = time;
+        self.current.frames.push(frame);
+    }
+
+    /// Records the pickup of the collectible that spawned at `key`.
+    pub fn split(&mut self, key: SplitKey, time: f32) {
+        self.current.splits.push((key, time));
+    }
+
+    /// Returns true if the collectible that spawned at `key` has been picked up in the current run.
+    pub fn is_collected(&self, key: SplitKey) -> bool {
+        self.current.splits.iter().any(|(k, _)| *k == key)
+    }
+
+    /// Returns the latest split, compared to the time the best run picked up
+    /// the same collectible, so that collecting in a different order still compares like with like.
+    pub fn latest_split(&self) -> Option<Split> {
+        let number = self.current.splits.len();
+        let (key, time) = *self.current.splits.last()?;
+        let best = self
+            .best
+            .as_ref()
+            .and_then(|b| b.splits.iter().find(|(k, _)| *k == key));
+        Some(Split {
+            number,
+            time,
+            delta: best.map(|(_, best)| time - best),
+        })
+    }
+
+    /// Returns the ghost frame of the best run for the latest recorded step,
+    /// or `None` if there is no best run or it has already ended.
+    pub fn ghost(&self) -> Option<&GhostFrame> {
+        let step = self.current.frames.len().checked_sub(1)?;
+        self.best.as_ref()?.frames.get(step)
+    }
+
+    /// Ends the current run. Returns the run if it was faster than the best run,
+    /// and replaces the best run with it.
+    pub fn finish(&mut self) -> Option<&Run> {
+        let run = std::mem::take(&mut self.current);
+        if self.best.as_ref().is_some_and(|best| best.time <= run.time) {
+            return None;
+        }
+        self.best = Some(run);
+        self.best.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(x: f32) -> GhostFrame {
+        GhostFrame {
+            pos: Point2::new(x, 32.5),
+            facing: Direction::Left,
+            animation: String::from("run"),
+            frame: 4,
+        }
+    }
+
+    fn run(time: f32, splits: Vec<(SplitKey, f32)>) -> Run {
+        Run {
+            time,
+            splits,
+            frames: vec![frame(1.0), frame(2.0)],
+        }
+    }
+
+    #[test]
+    fn run_round_trip() {
+        let run = run(12.25, vec![((3, 4), 3.5), ((10, 2), 7.0)]);
+        let text = DataFile::to_text(GHOST_VERSION, &run.entries());
+        assert_eq!(Run::parse(&text), Ok(run));
+        assert!(Run::parse("version 2\nframe 1 2 up run 0\n").is_err());
+        assert!(Run::parse("version 2\nsplit 3.5\n").is_err());
+        assert!(Run::parse("time 1\n").is_err());
+    }
+
+    #[test]
+    fn drops_unkeyed_splits_of_version_1() {
+        let run = Run::parse("version 1\ntime 9.5\nsplit 3.5\nframe 1 2 left run 0\n").unwrap();
+        assert_eq!((run.time, run.splits.len(), run.frames.len()), (9.5, 0, 1));
+    }
+
+    #[test]
+    fn compares_splits_to_same_collectible_of_best_run() {
+        let best = vec![((1, 1), 2.0), ((5, 1), 5.0)];
+        let mut speedrun = Speedrun::new(Some(run(10.0, best)));
+        assert_eq!(speedrun.latest_split(), None);
+        // Collected in the opposite order from the best run
+        speedrun.split((5, 1), 4.0);
+        let split = speedrun.latest_split().unwrap();
+        assert_eq!(
+            (split.number, split.time, split.delta),
+            (1, 4.0, Some(-1.0))
+        );
+        assert!(speedrun.is_collected((5, 1)) && !speedrun.is_collected((1, 1)));
+        speedrun.split((1, 1), 6.0);
+        assert_eq!(speedrun.latest_split().unwrap().delta, Some(4.0));
+        speedrun.split((9, 9), 7.0);
+        assert_eq!(speedrun.latest_split().unwrap().delta, None);
+    }
+
+    #[test]
+    fn ghost_follows_recorded_steps() {
+        let mut speedrun = Speedrun::new(Some(run(10.0, vec![])));
+        assert_eq!(speedrun.ghost(), None);
+        speedrun.record(0.1, frame(5.0));
+        assert_eq!(speedrun.ghost(), Some(&frame(1.0)));
+        speedrun.record(0.2, frame(5.0));
+        speedrun.record(0.3, frame(5.0));
+        assert_eq!(speedrun.ghost(), None);
+    }
+
+    #[test]
+    fn keeps_fastest_run() {
+        let mut speedrun = Speedrun::new(Some(run(10.0, vec![])));
+        speedrun.record(11.0, frame(0.0));
+        assert!(speedrun.finish().is_none());
+        speedrun.record(9.0, frame(0.0));
+        assert_eq!(speedrun.finish().map(|r| r.time), Some(9.0));
+        assert!(speedrun.current.frames.is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+use ggez::glam::Vec2;
+use ggez::graphics::{Canvas, Color, DrawParam, Image, Rect, Sampler};
+use ggez::{Context, GameResult};
+
+use crate::constants::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// How the game's fixed resolution image is fitted to the window.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScaleMode {
+    /// Largest whole-number scale that fits the window, so that all pixels are the same size.
+    /// Falls back to letterboxing if the window is smaller than the game.
+    Integer,
+    /// Largest scale that fits the window, keeping the aspect ratio.
+    Letterbox,
+    /// Fills the whole window, ignoring the aspect ratio.
+    Stretch,
+}
+
+impl ScaleMode {
+    pub fn parse(name: &str) -> Option<ScaleMode> {
+        match name {
+            "integer" => Some(ScaleMode::Integer),
+            "letterbox" => Some(ScaleMode::Letterbox),
+            "stretch" => Some(ScaleMode::Stretch),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ScaleMode::Integer => "integer",
+            ScaleMode::Letterbox => "letterbox",
+            ScaleMode::Stretch => "stretch",
+        }
+    }
+
+    pub fn next(self) -> ScaleMode {
+        match self {
+            ScaleMode::Integer => ScaleMode::Letterbox,
+            ScaleMode::Letterbox => ScaleMode::Stretch,
+            ScaleMode::Stretch => ScaleMode::Integer,
+        }
+    }
+}
+
+/// Offscreen canvas with the game's virtual resolution (`SCREEN_WIDTH` x `SCREEN_HEIGHT`),
+/// scaled to the window when the frame is presented.
+pub struct Viewport {
+    pub mode: ScaleMode,
+    image: Image,
+    /// Window drawable size, in physical pixels
+    window_size: Vec2,
+}
+
+impl Viewport {
+    pub fn new(ctx: &Context, mode: ScaleMode) -> Viewport {
+        let image = Image::new_canvas_image(
+            ctx,
+            ctx.gfx.surface_format(),
+            SCREEN_WIDTH as u32,
+            SCREEN_HEIGHT as u32,
+            1,
+        );
+        let (width, height) = ctx.gfx.drawable_size();
+        Viewport {
+            mode,
+            image,
+            window_size: Vec2::new(width, height),
+        }
+    }
+
+    /// Called when the window has been resized.
+    pub fn resize(&mut self, width: f32, height: f32) {
+        self.window_size = Vec2::new(width, height);
+    }
+
+    /// Returns a canvas for drawing the game at its virtual resolution.
+    pub fn canvas(&self, ctx: &Context) -> Canvas {
+        Canvas::from_image(ctx, self.image.clone(), Color::WHITE)
+    }
+
+    /// Converts a point in the window, such as the mouse position, to the game's
+    /// virtual resolution. Returns `None` for points outside the game image.
+    pub fn to_virtual(&self, point: Vec2) -> Option<Vec2> {
+        window_to_virtual(self.mode, self.window_size, point)
+    }
+
+    /// Draws the game image to the window, with black bars around it if needed.
+    pub fn present(&self, ctx: &mut Context) -> GameResult {
+        let mut canvas = Canvas::from_frame(ctx, Color::BLACK);
+        if self.mode == ScaleMode::Integer {
+            canvas.set_sampler(Sampler::nearest_clamp());
+        }
+        let virtual_size = Vec2::new(SCREEN_WIDTH, SCREEN_HEIGHT);
+        let target = target_rect(self.mode, self.window_size, virtual_size);
+        canvas.draw(
+            &self.image,
+            DrawParam::new()
+                .dest(target.point())
+                .scale(Vec2::new(target.w, target.h) / virtual_size),
+        );
+        canvas.finish(ctx)
+    }
+}
+
+/// Returns the area of the window that the game image is drawn to, in physical pixels.
+pub fn target_rect(mode: ScaleMode, window: Vec2, game: Vec2) -> Rect {
+    let fit = (window.x / game.x).min(window.y / game.y);
+    let scale = match mode {
+        ScaleMode::Stretch => return Rect::new(0.0, 0.0, window.x, window.y),
+        ScaleMode::Integer if fit >= 1.0 => fit.floor(),
+        ScaleMode::Integer | ScaleMode::Letterbox => fit,
+    };
+    let size = game * scale;
+    Rect::new(
+        ((window.x - size.x) / 2.0).round(),
+        ((window.y - size.y) / 2.0).round(),
+        size.x,
+        size.y,
+    )
+}
+
+fn window_to_virtual(mode: ScaleMode, window: Vec2, point: Vec2) -> Option<Vec2> {
+    let virtual_size = Vec2::new(SCREEN_WIDTH, SCREEN_HEIGHT);
+    let target = target_rect(mode, window, virtual_size);
+    let origin = Vec2::new(target.x, target.y);
+    let pos = (point - origin) / Vec2::new(target.w, target.h) * virtual_size;
+    let inside = pos.cmpge(Vec2::ZERO).all() && pos.cmplt(virtual_size).all();
+    inside.then_some(pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAME: Vec2 = Vec2::new(1280.0, 720.0);
+
+    #[test]
+    fn integer_scale_is_centered() {
+        let rect = target_rect(ScaleMode::Integer, Vec2::new(3000.0, 1600.0), GAME);
+        assert_eq!(rect, Rect::new(220.0, 80.0, 2560.0, 1440.0));
+    }
+
+    #[test]
+    fn letterbox_keeps_aspect_ratio() {
+        let rect = target_rect(ScaleMode::Letterbox, Vec2::new(1920.0, 1200.0), GAME);
+        assert_eq!(rect, Rect::new(0.0, 60.0, 1920.0, 1080.0));
+    }
+
+    #[test]
+    fn integer_scale_falls_back_to_letterbox_in_small_windows() {
+        let small = Vec2::new(640.0, 480.0);
+        assert_eq!(
+            target_rect(ScaleMode::Integer, small, GAME),
+            target_rect(ScaleMode::Letterbox, small, GAME)
+        );
+    }
+
+    #[test]
+    fn window_points_map_to_virtual_resolution() {
+        let window = Vec2::new(1920.0, 1200.0);
+        let center = window_to_virtual(ScaleMode::Letterbox, window, window / 2.0);
+        assert_eq!(center, Some(GAME / 2.0));
+        let corner = window_to_virtual(ScaleMode::Letterbox, window, Vec2::new(0.0, 60.0));
+        assert_eq!(corner, Some(Vec2::ZERO));
+        // On the black bar above the game image
+        assert_eq!(
+            window_to_virtual(ScaleMode::Letterbox, window, Vec2::new(10.0, 10.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn stretch_fills_window() {
+        let rect = target_rect(ScaleMode::Stretch, Vec2::new(800.0, 800.0), GAME);
+        assert_eq!(rect, Rect::new(0.0, 0.0, 800.0, 800.0));
+    }
+}